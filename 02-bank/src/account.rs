@@ -0,0 +1,613 @@
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+use super::bank::hold::Hold;
+use super::currency::BASE_CURRENCY;
+use super::customer::{AccountHolder, HolderRole};
+use super::report::{ReconciliationLine, Statement};
+use super::status::{AccountStatus, StatusChange};
+use super::transaction::{Transaction, TransactionKind};
+
+// Like 'StatusChange', every new limit stays on record
+#[derive(Debug, Clone)]
+pub struct OverdraftChange {
+    pub at: DateTime<Utc>,
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    // Only ever changed by deposits and withdrawals, so it
+    // always agrees with 'transactions'
+    balance: i32,
+    pub currency: String,
+    // How far below 0 the balance may go; changed through
+    // 'set_overdraft_limit_at' so every change is on record
+    overdraft_limit: i32,
+    overdraft_history: Vec<OverdraftChange>,
+    pub holders: Vec<AccountHolder>,
+    pub id: u32,
+    pub opened_at: DateTime<Utc>,
+    status: AccountStatus,
+    status_history: Vec<StatusChange>,
+    transactions: Vec<Transaction>,
+    holds: Vec<Hold>,
+    next_hold_id: u32,
+    // When the status, overdraft limit or holds last
+    // changed; see 'check_not_settled'
+    settled_at: Option<DateTime<Utc>>,
+}
+
+// Inherent implementation
+impl Account {
+    // Every account starts out with a single owner
+    pub fn new(id: u32, owner_id: u32) -> Self {
+        // Implicit return
+        Account {
+            balance: 0,
+            currency: String::from(BASE_CURRENCY),
+            overdraft_limit: 0,
+            overdraft_history: Vec::new(),
+            holders: vec![AccountHolder {
+                customer_id: owner_id,
+                role: HolderRole::Owner,
+            }],
+            id,
+            opened_at: Utc::now(),
+            status: AccountStatus::Active,
+            status_history: Vec::new(),
+            transactions: Vec::new(),
+            holds: Vec::new(),
+            next_hold_id: 1,
+            settled_at: None,
+        }
+    }
+
+    pub fn balance(&self) -> i32 {
+        self.balance
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "AccID: {} has balance {} {} ({:?})",
+            self.id, self.balance, self.currency, self.status
+        )
+    }
+
+    pub fn role_of(&self, customer_id: u32) -> Option<HolderRole> {
+        self.holders
+            .iter()
+            .find(|holder| holder.customer_id == customer_id)
+            .map(|holder| holder.role)
+    }
+
+    pub fn add_holder(&mut self, customer_id: u32, role: HolderRole) -> Result<(), Error> {
+        if self.role_of(customer_id).is_some() {
+            return Err(Error::other(format!(
+                "customer {} already holds account {}",
+                customer_id, self.id
+            )));
+        }
+
+        self.holders.push(AccountHolder { customer_id, role });
+        Ok(())
+    }
+
+    pub fn remove_holder(&mut self, customer_id: u32) -> Result<(), Error> {
+        let owners = self
+            .holders
+            .iter()
+            .filter(|holder| holder.role == HolderRole::Owner)
+            .count();
+
+        match self.role_of(customer_id) {
+            None => Err(Error::other(format!(
+                "customer {} doesn't hold account {}",
+                customer_id, self.id
+            ))),
+            Some(HolderRole::Owner) if owners == 1 => Err(Error::other(format!(
+                "can't remove the last owner of account {}",
+                self.id
+            ))),
+            Some(..) => {
+                self.holders
+                    .retain(|holder| holder.customer_id != customer_id);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn status(&self) -> AccountStatus {
+        self.status
+    }
+
+    pub fn status_history(&self) -> &Vec<StatusChange> {
+        &self.status_history
+    }
+
+    pub fn overdraft_limit(&self) -> i32 {
+        self.overdraft_limit
+    }
+
+    pub fn overdraft_history(&self) -> &Vec<OverdraftChange> {
+        &self.overdraft_history
+    }
+
+    pub fn set_overdraft_limit_at(&mut self, limit: i32, at: DateTime<Utc>) -> Result<(), Error> {
+        if limit < 0 {
+            return Err(Error::other("overdraft limit can't be negative"));
+        }
+
+        self.check_change_at(at)?;
+
+        if (self.balance as i64) < -(limit as i64) {
+            return Err(Error::other(format!(
+                "account {} is at {}, below an overdraft limit of {}",
+                self.id, self.balance, limit
+            )));
+        }
+
+        self.overdraft_history.push(OverdraftChange {
+            at,
+            from: self.overdraft_limit,
+            to: limit,
+        });
+        self.overdraft_limit = limit;
+        self.settled_at = Some(at);
+
+        Ok(())
+    }
+
+    pub fn transactions(&self) -> &Vec<Transaction> {
+        &self.transactions
+    }
+
+    pub fn deposit(&mut self, amount: i32) -> Result<i32, Error> {
+        self.deposit_at(amount, Utc::now())
+    }
+
+    pub fn withdraw(&mut self, amount: i32) -> Result<i32, Error> {
+        self.withdraw_at(amount, Utc::now())
+    }
+
+    // Same as 'deposit' but for money that arrived at a known
+    // point in time (imports, back-dated corrections etc.)
+    pub fn deposit_at(&mut self, amount: i32, at: DateTime<Utc>) -> Result<i32, Error> {
+        self.check_deposit(amount, at)?;
+
+        self.balance += amount;
+        self.record(TransactionKind::Deposit, amount, at);
+        Ok(self.balance)
+    }
+
+    pub fn withdraw_at(&mut self, amount: i32, at: DateTime<Utc>) -> Result<i32, Error> {
+        self.check_withdrawal(amount, at)?;
+
+        self.balance -= amount;
+        self.record(TransactionKind::Withdrawal, amount, at);
+        Ok(self.balance)
+    }
+
+    // Everything 'deposit_at' checks, without touching the
+    // account; lets a transfer vet both legs before moving
+    // any money
+    pub fn check_deposit(&self, amount: i32, at: DateTime<Utc>) -> Result<(), Error> {
+        if amount <= 0 {
+            return Err(Error::other("deposit amount must be positive"));
+        }
+
+        if at < self.opened_at {
+            return Err(Error::other(format!(
+                "account {} wasn't open yet at {}",
+                self.id, at
+            )));
+        }
+
+        if !self.status.accepts_deposits() {
+            return Err(Error::other(format!(
+                "account {} is {:?} and can't accept deposits",
+                self.id, self.status
+            )));
+        }
+
+        self.check_not_settled(at)?;
+
+        // Back-dated deposits raise the balance from 'at'
+        // onwards, so they have to fit at every point since
+        let (_, highest) = self.balance_range_from(at);
+        if i32::try_from(highest + amount as i64).is_err() {
+            return Err(Error::other(format!(
+                "depositing {} would overflow the balance of account {}",
+                amount, self.id
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_withdrawal(&self, amount: i32, at: DateTime<Utc>) -> Result<(), Error> {
+        if amount <= 0 {
+            return Err(Error::other("withdrawal amount must be positive"));
+        }
+
+        if at < self.opened_at {
+            return Err(Error::other(format!(
+                "account {} wasn't open yet at {}",
+                self.id, at
+            )));
+        }
+
+        if !self.status.accepts_withdrawals() {
+            return Err(Error::other(format!(
+                "account {} is {:?} and can't accept withdrawals",
+                self.id, self.status
+            )));
+        }
+
+        self.check_not_settled(at)?;
+        self.check_available(amount, at)?;
+
+        if self.balance.checked_sub(amount).is_none() {
+            return Err(Error::other(format!(
+                "withdrawing {} would overflow the balance of account {}",
+                amount, self.id
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Only money may be back-dated. Status and limit changes
+    // come after everything the account has seen so far,
+    // holds after the last such change, and no money can be
+    // back-dated past any of them; otherwise replaying in
+    // time order could, say, run a withdrawal into a freeze
+    // that only came after it
+    fn check_not_settled(&self, at: DateTime<Utc>) -> Result<(), Error> {
+        match self.settled_at {
+            Some(settled_at) if at < settled_at => Err(Error::other(format!(
+                "account {} last changed at {}; nothing can be back-dated before that",
+                self.id, settled_at
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_change_at(&self, at: DateTime<Utc>) -> Result<(), Error> {
+        if at < self.opened_at {
+            return Err(Error::other(format!(
+                "account {} wasn't open yet at {}",
+                self.id, at
+            )));
+        }
+
+        self.check_not_settled(at)?;
+
+        match self.transactions.last() {
+            Some(last) if last.at > at => Err(Error::other(format!(
+                "account {} has money moving at {}; it can't change before that",
+                self.id, last.at
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // A back-dated withdrawal lowers the balance from 'at'
+    // onwards, so it has to fit at every point since then,
+    // not just today; otherwise replaying the events in
+    // time order would overdraw the account
+    fn check_available(&self, amount: i32, at: DateTime<Utc>) -> Result<(), Error> {
+        let (lowest, _) = self.balance_range_from(at);
+        let held = self.balance as i64 - self.available_balance(at);
+        let available = lowest - held + self.overdraft_limit as i64;
+
+        if amount as i64 > available {
+            return Err(match self.transactions.last() {
+                Some(last) if last.at > at => Error::other(format!(
+                    "account {} has {} available from {} on, can't withdraw {}",
+                    self.id, available, at, amount
+                )),
+                _ => Error::other(format!(
+                    "account {} has {} available, can't withdraw {}",
+                    self.id, available, amount
+                )),
+            });
+        }
+
+        Ok(())
+    }
+
+    // The lowest and highest the balance has been at 'at'
+    // or at any point after it
+    fn balance_range_from(&self, at: DateTime<Utc>) -> (i64, i64) {
+        // Whatever the account held before its first
+        // transaction was recorded
+        let mut balance = self.balance as i64 - self.ledger_balance();
+        let mut range = None;
+
+        for transaction in &self.transactions {
+            if transaction.at > at && range.is_none() {
+                range = Some((balance, balance));
+            }
+            balance += transaction.signed_amount() as i64;
+            if let Some((lowest, highest)) = range.as_mut() {
+                *lowest = balance.min(*lowest);
+                *highest = balance.max(*highest);
+            }
+        }
+
+        range.unwrap_or((balance, balance))
+    }
+
+    pub fn holds(&self) -> &Vec<Hold> {
+        &self.holds
+    }
+
+    // The balance minus whatever is still on hold at 'at';
+    // sums of balances are i64 so they can't overflow
+    pub fn available_balance(&self, at: DateTime<Utc>) -> i64 {
+        let held: i64 = self
+            .holds
+            .iter()
+            .filter(|hold| hold.is_active(at))
+            .map(|hold| hold.amount as i64)
+            .sum();
+
+        self.balance as i64 - held
+    }
+
+    pub fn place_hold(
+        &mut self,
+        amount: i32,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<u32, Error> {
+        if amount <= 0 {
+            return Err(Error::other("hold amount must be positive"));
+        }
+
+        if !self.status.accepts_withdrawals() {
+            return Err(Error::other(format!(
+                "account {} is {:?} and can't place holds",
+                self.id, self.status
+            )));
+        }
+
+        self.check_not_settled(at)?;
+        self.check_available(amount, at)?;
+
+        let id = self.next_hold_id;
+        self.next_hold_id += 1;
+        self.holds.push(Hold {
+            id,
+            amount,
+            placed_at: at,
+            expires_at,
+        });
+        self.settled_at = Some(at);
+
+        Ok(id)
+    }
+
+    // Captures 'amount' (up to the full hold) and releases
+    // the rest
+    pub fn capture_hold(
+        &mut self,
+        hold_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        let position = match self.holds.iter().position(|hold| hold.id == hold_id) {
+            Some(position) => position,
+            None => {
+                return Err(Error::other(format!(
+                    "account {} has no hold {}",
+                    self.id, hold_id
+                )))
+            }
+        };
+
+        let hold = &self.holds[position];
+        if !hold.is_active(at) {
+            return Err(Error::other(format!(
+                "hold {} expired at {}",
+                hold_id, hold.expires_at
+            )));
+        }
+
+        if amount > hold.amount {
+            return Err(Error::other(format!(
+                "can't capture {} from a hold of {}",
+                amount, hold.amount
+            )));
+        }
+
+        // The hold has to go before the withdrawal, otherwise
+        // the money would be counted as unavailable twice
+        let hold = self.holds.remove(position);
+        match self.withdraw_at(amount, at) {
+            Ok(balance) => {
+                self.settled_at = Some(at);
+                Ok(balance)
+            }
+            Err(reason_capture_failed) => {
+                self.holds.insert(position, hold);
+                Err(reason_capture_failed)
+            }
+        }
+    }
+
+    pub fn release_hold(&mut self, hold_id: u32, at: DateTime<Utc>) -> Result<i32, Error> {
+        self.check_not_settled(at)?;
+
+        match self.holds.iter().position(|hold| hold.id == hold_id) {
+            Some(position) => {
+                self.settled_at = Some(at);
+                Ok(self.holds.remove(position).amount)
+            }
+            None => Err(Error::other(format!(
+                "account {} has no hold {}",
+                self.id, hold_id
+            ))),
+        }
+    }
+
+    pub fn expire_holds(&mut self, at: DateTime<Utc>) -> Vec<Hold> {
+        let (active, expired) = self.holds.drain(..).partition(|hold| hold.is_active(at));
+        self.holds = active;
+        expired
+    }
+
+    // A single hold out of what 'expire_holds' would drop
+    pub fn expire_hold(&mut self, hold_id: u32, at: DateTime<Utc>) -> Result<Hold, Error> {
+        match self.holds.iter().position(|hold| hold.id == hold_id) {
+            Some(position) if !self.holds[position].is_active(at) => {
+                Ok(self.holds.remove(position))
+            }
+            Some(..) => Err(Error::other(format!(
+                "hold {} is still active at {}",
+                hold_id, at
+            ))),
+            None => Err(Error::other(format!(
+                "account {} has no hold {}",
+                self.id, hold_id
+            ))),
+        }
+    }
+
+    // The ledger is kept in time order, so back-dated
+    // transactions slot in where they belong
+    fn record(&mut self, kind: TransactionKind, amount: i32, at: DateTime<Utc>) {
+        let position = self
+            .transactions
+            .partition_point(|transaction| transaction.at <= at);
+
+        self.transactions
+            .insert(position, Transaction { at, kind, amount });
+    }
+
+    // Balance derived purely from the ledger, counting
+    // every transaction before 'at'
+    pub fn ledger_balance_before(&self, at: DateTime<Utc>) -> i64 {
+        self.transactions
+            .iter()
+            .filter(|transaction| transaction.at < at)
+            .map(|transaction| transaction.signed_amount() as i64)
+            .sum()
+    }
+
+    pub fn ledger_balance(&self) -> i64 {
+        self.transactions
+            .iter()
+            .map(|transaction| transaction.signed_amount() as i64)
+            .sum()
+    }
+
+    pub fn statement(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Statement<'_> {
+        Statement {
+            account_id: self.id,
+            from,
+            to,
+            opening_balance: self.ledger_balance_before(from),
+            transactions: self
+                .transactions
+                .iter()
+                .filter(|transaction| transaction.at >= from && transaction.at < to)
+                .collect(),
+            closing_balance: self.ledger_balance_before(to),
+        }
+    }
+
+    pub fn reconcile(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> ReconciliationLine {
+        let statement = self.statement(from, to);
+        let (credits, debits) =
+            statement
+                .transactions
+                .iter()
+                .fold((0, 0), |(credits, debits), transaction| {
+                    match transaction.kind {
+                        TransactionKind::Deposit => (credits + transaction.amount as i64, debits),
+                        TransactionKind::Withdrawal => {
+                            (credits, debits + transaction.amount as i64)
+                        }
+                    }
+                });
+
+        ReconciliationLine {
+            account_id: self.id,
+            opening_balance: statement.opening_balance,
+            credits,
+            debits,
+            closing_balance: statement.closing_balance,
+            ledger_balance: self.ledger_balance(),
+            stored_balance: self.balance as i64,
+        }
+    }
+
+    pub fn freeze(&mut self, reason: String) -> Result<(), Error> {
+        self.change_status_at(AccountStatus::Frozen, reason, Utc::now())
+    }
+
+    pub fn mark_dormant(&mut self, reason: String) -> Result<(), Error> {
+        self.change_status_at(AccountStatus::Dormant, reason, Utc::now())
+    }
+
+    // Brings a frozen or dormant account back to life
+    pub fn activate(&mut self, reason: String) -> Result<(), Error> {
+        self.change_status_at(AccountStatus::Active, reason, Utc::now())
+    }
+
+    pub fn close(&mut self, reason: String) -> Result<(), Error> {
+        self.change_status_at(AccountStatus::Closed, reason, Utc::now())
+    }
+
+    // Same as 'freeze', 'activate' etc. but for a change that
+    // took effect at a known point in time
+    pub fn change_status_at(
+        &mut self,
+        to: AccountStatus,
+        reason: String,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        if !self.status.can_transition_to(to) {
+            return Err(Error::other(format!(
+                "account {} can't go from {:?} to {:?}",
+                self.id, self.status, to
+            )));
+        }
+
+        if to == AccountStatus::Closed {
+            if self.balance != 0 {
+                return Err(Error::other(format!(
+                    "account {} can't be closed with a balance of {}",
+                    self.id, self.balance
+                )));
+            }
+
+            // Even expired ones; a hold is only gone once it's
+            // been captured, released or swept by 'expire_holds'
+            if !self.holds.is_empty() {
+                return Err(Error::other(format!(
+                    "account {} can't be closed with {} hold(s) on it",
+                    self.id,
+                    self.holds.len()
+                )));
+            }
+        }
+
+        self.check_change_at(at)?;
+
+        self.status_history.push(StatusChange {
+            at,
+            from: self.status,
+            to,
+            reason,
+        });
+        self.status = to;
+        self.settled_at = Some(at);
+
+        Ok(())
+    }
+}
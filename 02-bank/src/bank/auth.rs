@@ -0,0 +1,322 @@
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+use super::loan::Loan;
+use super::Bank;
+use crate::customer::HolderRole;
+use crate::ledger::JournalLine;
+use crate::status::AccountStatus;
+
+// Whoever is asking; the number is their customer or staff
+// id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    Customer(u32),
+    Teller(u32),
+    Admin(u32),
+    Auditor(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Deposit {
+        account_id: u32,
+        amount: i32,
+    },
+    Withdraw {
+        account_id: u32,
+        amount: i32,
+    },
+    Transfer {
+        from_id: u32,
+        to_id: u32,
+        amount: i32,
+    },
+    CloseAccount {
+        account_id: u32,
+        reason: String,
+    },
+    PlaceHold {
+        account_id: u32,
+        amount: i32,
+    },
+    // Turns (part of) a hold into a withdrawal
+    CaptureHold {
+        account_id: u32,
+        hold_id: u32,
+        amount: i32,
+    },
+    ReleaseHold {
+        account_id: u32,
+        hold_id: u32,
+    },
+    // Pays the principal out into 'account_id'
+    DisburseLoan {
+        loan: Loan,
+        account_id: u32,
+    },
+    RepayLoan {
+        loan_id: u32,
+        account_id: u32,
+        amount: i32,
+    },
+    Freeze {
+        account_id: u32,
+        reason: String,
+    },
+    MarkDormant {
+        account_id: u32,
+        reason: String,
+    },
+    Activate {
+        account_id: u32,
+        reason: String,
+    },
+    SetOverdraftLimit {
+        account_id: u32,
+        limit: i32,
+    },
+    // A journal entry that doesn't touch any customer
+    // account
+    PostEntry {
+        description: String,
+        lines: Vec<JournalLine>,
+    },
+}
+
+// Anything an operation hands back besides its effect on
+// the bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    HoldPlaced { hold_id: u32 },
+    // In the target account's currency
+    Transferred { credited: i32 },
+}
+
+/*
+    ### Permissions ###
+        - Admin: everything
+        - Teller: deposits, withdrawals, transfers, card
+        holds and loan repayments, but no closing accounts,
+        changing their status or overdraft limit, paying
+        out loans or posting journal entries
+        - Auditor: read-only, so nothing here
+        - Customer: only on accounts they hold, and never
+        what a teller can't do either
+            - Owner: everything
+            - Authorized signer: everything but closing
+            (card holds and loan repayments count as
+            withdrawals)
+            - Viewer: nothing
+*/
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub actor: Actor,
+    pub operation: Operation,
+    pub allowed: bool,
+    // Why it was denied, or why it failed after being
+    // allowed; empty when everything went fine
+    pub reason: String,
+}
+
+// Append-only; entries can be read but never changed
+#[derive(Debug)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+// Inherent implementation
+impl AuditLog {
+    pub fn new() -> Self {
+        AuditLog {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, entry: AuditEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &Vec<AuditEntry> {
+        &self.entries
+    }
+
+    pub fn denied(&self) -> Vec<&AuditEntry> {
+        self.entries.iter().filter(|entry| !entry.allowed).collect()
+    }
+}
+
+// Inherent implementation
+impl Bank {
+    pub fn authorize(&self, actor: Actor, operation: &Operation) -> Result<(), Error> {
+        let customer_id = match actor {
+            Actor::Admin(..) => return Ok(()),
+            Actor::Teller(..) => {
+                return match operation {
+                    Operation::CloseAccount { .. } => {
+                        Err(Error::other("tellers can't close accounts"))
+                    }
+                    Operation::Freeze { .. }
+                    | Operation::MarkDormant { .. }
+                    | Operation::Activate { .. } => {
+                        Err(Error::other("tellers can't change an account's status"))
+                    }
+                    Operation::DisburseLoan { .. } => {
+                        Err(Error::other("tellers can't pay out loans"))
+                    }
+                    Operation::SetOverdraftLimit { .. } => {
+                        Err(Error::other("tellers can't change overdraft limits"))
+                    }
+                    Operation::PostEntry { .. } => {
+                        Err(Error::other("tellers can't post journal entries"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Actor::Auditor(..) => return Err(Error::other("auditors have read-only access")),
+            Actor::Customer(customer_id) => customer_id,
+        };
+
+        let (account_id, allowed_roles) = match operation {
+            Operation::Deposit { account_id, .. }
+            | Operation::Withdraw { account_id, .. }
+            | Operation::PlaceHold { account_id, .. }
+            | Operation::CaptureHold { account_id, .. }
+            | Operation::ReleaseHold { account_id, .. }
+            | Operation::RepayLoan { account_id, .. } => (
+                *account_id,
+                vec![HolderRole::Owner, HolderRole::AuthorizedSigner],
+            ),
+            // Sending money needs rights on the source
+            // account only
+            Operation::Transfer { from_id, .. } => (
+                *from_id,
+                vec![HolderRole::Owner, HolderRole::AuthorizedSigner],
+            ),
+            Operation::CloseAccount { account_id, .. } => (*account_id, vec![HolderRole::Owner]),
+            // Staff decisions, whatever the customer's role
+            Operation::Freeze { account_id, .. }
+            | Operation::MarkDormant { account_id, .. }
+            | Operation::Activate { account_id, .. }
+            | Operation::DisburseLoan { account_id, .. }
+            | Operation::SetOverdraftLimit { account_id, .. } => (*account_id, vec![]),
+            Operation::PostEntry { .. } => {
+                return Err(Error::other("customers can't post journal entries"))
+            }
+        };
+
+        let role = self
+            .account(account_id)
+            .and_then(|account| account.role_of(customer_id));
+
+        match role {
+            Some(role) if allowed_roles.contains(&role) => Ok(()),
+            Some(role) => Err(Error::other(format!(
+                "customer {} is {:?} on account {} and can't do that",
+                customer_id, role, account_id
+            ))),
+            None => Err(Error::other(format!(
+                "customer {} doesn't hold account {}",
+                customer_id, account_id
+            ))),
+        }
+    }
+
+    // The front door for anything a person asks the bank to
+    // do; every attempt lands in the audit log
+    pub fn execute(
+        &mut self,
+        actor: Actor,
+        operation: Operation,
+        at: DateTime<Utc>,
+    ) -> Result<Outcome, Error> {
+        if let Err(reason_denied) = self.authorize(actor, &operation) {
+            self.audit_log.record(AuditEntry {
+                at,
+                actor,
+                operation,
+                allowed: false,
+                reason: reason_denied.to_string(),
+            });
+
+            return Err(reason_denied);
+        }
+
+        let result = match &operation {
+            Operation::Deposit { account_id, amount } => self
+                .deposit_at(*account_id, *amount, at)
+                .map(|_| Outcome::Done),
+            Operation::Withdraw { account_id, amount } => self
+                .withdraw_at(*account_id, *amount, at)
+                .map(|_| Outcome::Done),
+            Operation::Transfer {
+                from_id,
+                to_id,
+                amount,
+            } => self
+                .transfer_at(*from_id, *to_id, *amount, at)
+                .map(|credited| Outcome::Transferred { credited }),
+            Operation::CloseAccount { account_id, reason } => self
+                .change_status(*account_id, AccountStatus::Closed, reason.clone(), at)
+                .map(|_| Outcome::Done),
+            Operation::Freeze { account_id, reason } => self
+                .change_status(*account_id, AccountStatus::Frozen, reason.clone(), at)
+                .map(|_| Outcome::Done),
+            Operation::MarkDormant { account_id, reason } => self
+                .change_status(*account_id, AccountStatus::Dormant, reason.clone(), at)
+                .map(|_| Outcome::Done),
+            Operation::Activate { account_id, reason } => self
+                .change_status(*account_id, AccountStatus::Active, reason.clone(), at)
+                .map(|_| Outcome::Done),
+            Operation::SetOverdraftLimit { account_id, limit } => self
+                .set_overdraft_limit(*account_id, *limit, at)
+                .map(|_| Outcome::Done),
+            Operation::PostEntry { description, lines } => self
+                .post_entry(description.clone(), lines.clone(), at)
+                .map(|_| Outcome::Done),
+            Operation::PlaceHold { account_id, amount } => self
+                .place_hold(*account_id, *amount, at)
+                .map(|hold_id| Outcome::HoldPlaced { hold_id }),
+            Operation::CaptureHold {
+                account_id,
+                hold_id,
+                amount,
+            } => self
+                .capture_hold(*account_id, *hold_id, *amount, at)
+                .map(|_| Outcome::Done),
+            Operation::ReleaseHold {
+                account_id,
+                hold_id,
+            } => self
+                .release_hold(*account_id, *hold_id, at)
+                .map(|_| Outcome::Done),
+            Operation::DisburseLoan { loan, account_id } => self
+                .disburse_loan(loan.clone(), *account_id, at)
+                .map(|_| Outcome::Done),
+            Operation::RepayLoan {
+                loan_id,
+                account_id,
+                amount,
+            } => self
+                .repay_loan(*loan_id, *account_id, *amount, at)
+                .map(|_| Outcome::Done),
+        };
+
+        self.audit_log.record(AuditEntry {
+            at,
+            actor,
+            operation,
+            allowed: true,
+            reason: match &result {
+                Ok(..) => String::new(),
+                Err(reason_failed) => reason_failed.to_string(),
+            },
+        });
+
+        result
+    }
+}
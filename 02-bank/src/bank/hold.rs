@@ -0,0 +1,202 @@
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+use super::event::BankEvent;
+use super::Bank;
+use crate::ledger::{customer_deposits_code, JournalLine, CASH};
+use crate::transaction::TransactionKind;
+
+/*
+    ### Holds ###
+        - A hold sets money aside for a card payment that
+        hasn't settled yet
+        - Held money can't be withdrawn (lower available
+        balance) but it hasn't left the account either
+        (same ledger balance)
+        - Capturing turns (part of) the hold into a real
+        withdrawal; whatever isn't captured is released
+        - Holds nobody captured simply stop counting once
+        they expire
+        - An account with a hold on it can't be closed until
+        the hold is captured, released or swept
+        - People place, capture and release holds through
+        'Bank::execute', like any other withdrawal
+        - Placing, capturing, releasing and sweeping are all
+        events; replaying them rebuilds the same holds under
+        the same ids
+*/
+
+#[derive(Debug, Clone)]
+pub struct Hold {
+    pub id: u32,
+    pub amount: i32,
+    pub placed_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Inherent implementation
+impl Hold {
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        at < self.expires_at
+    }
+}
+
+// Inherent implementation
+impl Bank {
+    pub(super) fn place_hold(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<u32, Error> {
+        // Card authorizations go through the same fraud
+        // checks as withdrawals
+        self.screen(account_id, TransactionKind::Withdrawal, amount, at)?;
+
+        let expires_at = at + self.hold_expiry;
+        self.apply_hold(account_id, amount, at, expires_at)
+    }
+
+    // 'place_hold' once the hold has been screened and its
+    // expiry worked out; replaying a 'HoldPlaced' event
+    // lands here
+    pub(super) fn apply_hold(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<u32, Error> {
+        let hold_id = match self.account_mut(account_id) {
+            Some(account) => account.place_hold(amount, at, expires_at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.record_event(
+            at,
+            BankEvent::HoldPlaced {
+                account_id,
+                hold_id,
+                amount,
+                expires_at,
+            },
+        );
+
+        Ok(hold_id)
+    }
+
+    pub(super) fn capture_hold(
+        &mut self,
+        account_id: u32,
+        hold_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        // The authorization was screened when the hold was
+        // placed, but what's captured is a withdrawal of its
+        // own and may differ from it
+        self.screen(account_id, TransactionKind::Withdrawal, amount, at)?;
+
+        self.apply_capture(account_id, hold_id, amount, at)
+    }
+
+    pub(super) fn apply_capture(
+        &mut self,
+        account_id: u32,
+        hold_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        let balance = match self.account_mut(account_id) {
+            Some(account) => account.capture_hold(hold_id, amount, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.ledger.post(
+            at,
+            format!("Card payment from account {}", account_id),
+            vec![
+                JournalLine::debit(&customer_deposits_code(account_id), amount),
+                JournalLine::credit(CASH, amount),
+            ],
+        )?;
+        self.record_event(
+            at,
+            BankEvent::HoldCaptured {
+                account_id,
+                hold_id,
+                amount,
+            },
+        );
+
+        Ok(balance)
+    }
+
+    pub(super) fn release_hold(
+        &mut self,
+        account_id: u32,
+        hold_id: u32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        let amount = match self.account_mut(account_id) {
+            Some(account) => account.release_hold(hold_id, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.record_event(
+            at,
+            BankEvent::HoldReleased {
+                account_id,
+                hold_id,
+            },
+        );
+
+        Ok(amount)
+    }
+
+    // Drops every expired hold; returns how many there were
+    pub fn expire_holds(&mut self, at: DateTime<Utc>) -> usize {
+        let mut expired = Vec::new();
+
+        for account in self.accounts.iter_mut() {
+            for hold in account.expire_holds(at) {
+                expired.push(BankEvent::HoldExpired {
+                    account_id: account.id,
+                    hold_id: hold.id,
+                });
+            }
+        }
+
+        let count = expired.len();
+        for event in expired {
+            self.record_event(at, event);
+        }
+
+        count
+    }
+
+    // Replays one hold of a sweep; holds placed after the
+    // sweep ran, even back-dated ones, are left alone
+    pub(super) fn expire_hold(
+        &mut self,
+        account_id: u32,
+        hold_id: u32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        match self.account_mut(account_id) {
+            Some(account) => account.expire_hold(hold_id, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.record_event(
+            at,
+            BankEvent::HoldExpired {
+                account_id,
+                hold_id,
+            },
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+use super::auth::{Actor, Operation};
+use super::Bank;
+use crate::transaction::TransactionKind;
+
+/*
+    ### Batch file format ###
+        date,account_id,type,amount
+        2024-03-01T09:30:00Z,1,deposit,100
+        2024-03-01T10:00:00Z,2,withdrawal,40
+
+        - The header line is required
+        - 'type' is either 'deposit' or 'withdrawal'
+        - Fields are never quoted, so none of them may
+        contain a comma
+        - Every row goes through 'Bank::execute' on behalf
+        of whoever runs the import, so it is authorized and
+        audited like any other deposit or withdrawal
+*/
+const HEADER: &str = "date,account_id,type,amount";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    // A single bad row rejects the whole batch
+    AllOrNothing,
+    // Good rows are applied, bad rows are reported
+    SkipBadRows,
+}
+
+#[derive(Debug)]
+pub struct RejectedRow {
+    // 1-based, the header being line 1
+    pub line: usize,
+    pub row: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub applied: usize,
+    pub rejected: Vec<RejectedRow>,
+    // 'false' if the batch was rolled back
+    pub committed: bool,
+}
+
+// Inherent implementation
+impl ImportReport {
+    pub fn rejections_csv(&self) -> String {
+        let mut lines = vec![String::from("line,reason,row")];
+
+        for rejected in &self.rejected {
+            lines.push(format!(
+                "{},{},{}",
+                rejected.line,
+                quote(&rejected.reason),
+                quote(&rejected.row)
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn write_rejections(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, self.rejections_csv())
+    }
+}
+
+struct ImportRow {
+    line: usize,
+    row: String,
+    at: DateTime<Utc>,
+    account_id: u32,
+    kind: TransactionKind,
+    amount: i32,
+}
+
+// Inherent implementation
+impl Bank {
+    pub fn import_file(
+        &mut self,
+        actor: Actor,
+        path: &str,
+        mode: ImportMode,
+    ) -> Result<ImportReport, Error> {
+        let text = fs::read_to_string(path)?;
+        self.import_csv(actor, &text, mode)
+    }
+
+    pub fn import_csv(
+        &mut self,
+        actor: Actor,
+        text: &str,
+        mode: ImportMode,
+    ) -> Result<ImportReport, Error> {
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, header)) if header.trim() == HEADER => {}
+            _ => {
+                return Err(Error::other(format!(
+                    "batch files must start with '{}'",
+                    HEADER
+                )))
+            }
+        }
+
+        let mut rows = Vec::new();
+        let mut rejected = Vec::new();
+
+        for (index, row) in lines {
+            if row.trim().is_empty() {
+                continue;
+            }
+
+            match parse_row(index + 1, row) {
+                Ok(parsed) => rows.push(parsed),
+                Err(reason) => rejected.push(RejectedRow {
+                    line: index + 1,
+                    row: row.to_string(),
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+
+        // Rows are applied in file order, so a snapshot is
+        // the simplest way to undo a partially applied batch
+        let snapshot = self.snapshot();
+        let mut applied = 0;
+
+        for row in rows {
+            let operation = match row.kind {
+                TransactionKind::Deposit => Operation::Deposit {
+                    account_id: row.account_id,
+                    amount: row.amount,
+                },
+                TransactionKind::Withdrawal => Operation::Withdraw {
+                    account_id: row.account_id,
+                    amount: row.amount,
+                },
+            };
+
+            let result = self.execute(actor, operation, row.at);
+
+            match result {
+                Ok(..) => applied += 1,
+                Err(reason) => rejected.push(RejectedRow {
+                    line: row.line,
+                    row: row.row,
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+
+        rejected.sort_by_key(|rejected| rejected.line);
+
+        if mode == ImportMode::AllOrNothing && !rejected.is_empty() {
+            self.restore(snapshot);
+
+            return Ok(ImportReport {
+                applied: 0,
+                rejected,
+                committed: false,
+            });
+        }
+
+        Ok(ImportReport {
+            applied,
+            rejected,
+            committed: true,
+        })
+    }
+}
+
+fn parse_row(line: usize, row: &str) -> Result<ImportRow, Error> {
+    let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+
+    if fields.len() != 4 {
+        return Err(Error::other(format!(
+            "expected 4 fields but found {}",
+            fields.len()
+        )));
+    }
+
+    let at = DateTime::parse_from_rfc3339(fields[0])
+        .map_err(|_| Error::other(format!("invalid date '{}'", fields[0])))?
+        .with_timezone(&Utc);
+
+    let account_id = fields[1]
+        .parse::<u32>()
+        .map_err(|_| Error::other(format!("invalid account id '{}'", fields[1])))?;
+
+    let kind = match fields[2] {
+        "deposit" => TransactionKind::Deposit,
+        "withdrawal" => TransactionKind::Withdrawal,
+        other => return Err(Error::other(format!("unknown type '{}'", other))),
+    };
+
+    let amount = fields[3]
+        .parse::<i32>()
+        .map_err(|_| Error::other(format!("invalid amount '{}'", fields[3])))?;
+
+    Ok(ImportRow {
+        line,
+        row: row.to_string(),
+        at,
+        account_id,
+        kind,
+        amount,
+    })
+}
+
+// Wraps a CSV field in quotes, doubling any quotes inside
+fn quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
@@ -0,0 +1,509 @@
+use std::io::Error;
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+
+use super::event::BankEvent;
+use super::Bank;
+use crate::currency::{round_amount, BASE_CURRENCY};
+use crate::customer::HolderRole;
+use crate::ledger::{customer_deposits_code, JournalLine, INTEREST_INCOME, LOANS_RECEIVABLE};
+use crate::transaction::TransactionKind;
+
+// 50 years; also keeps every due date well inside what
+// 'NaiveDate' can represent
+pub const MAX_TERM_MONTHS: u32 = 600;
+
+/*
+    ### Amortization ###
+        - Every installment is the same fixed payment:
+            payment = principal * r / (1 - (1 + r)^-n)
+            where 'r' is the monthly rate and 'n' the
+            number of months
+        - Each payment first covers the interest on what's
+        still owed, the rest pays down the principal
+        - The last installment absorbs rounding so the loan
+        ends at exactly 0
+
+    ### Actual repayments ###
+        - Interest accrues daily (actual/365) on whatever
+        principal is really outstanding, so paying early or
+        paying extra saves interest
+        - The schedule is what the customer agreed to; it
+        is only used to work out arrears
+        - Amounts are i32 like balances, in the loan's
+        currency; sums are i64, and anything that wouldn't
+        fit an i32 is an error rather than a wrapped or
+        saturated number
+        - A loan is paid out into, and repaid from, accounts
+        in its own currency held by its customer
+*/
+
+#[derive(Debug, Clone)]
+pub struct Installment {
+    pub number: u32,
+    pub due: NaiveDate,
+    pub payment: i32,
+    pub interest: i32,
+    pub principal: i32,
+    // Principal left once this installment is paid
+    pub remaining: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repayment {
+    pub on: NaiveDate,
+    pub amount: i32,
+    pub interest: i32,
+    pub principal: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arrears {
+    pub missed_installments: u32,
+    pub amount_overdue: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loan {
+    pub id: u32,
+    pub customer_id: u32,
+    pub principal: i32,
+    pub currency: String,
+    // 100 bps = 1% a year
+    pub annual_rate_bps: u32,
+    pub term_months: u32,
+    pub start: NaiveDate,
+    outstanding: i32,
+    unpaid_interest: i32,
+    // Interest has been worked out up to this date
+    accrued_through: NaiveDate,
+    repayments: Vec<Repayment>,
+}
+
+// Inherent implementation
+impl Loan {
+    pub fn new(
+        id: u32,
+        customer_id: u32,
+        principal: i32,
+        annual_rate_bps: u32,
+        term_months: u32,
+        start: NaiveDate,
+    ) -> Result<Self, Error> {
+        if principal <= 0 {
+            return Err(Error::other("loan principal must be positive"));
+        }
+
+        if term_months == 0 || term_months > MAX_TERM_MONTHS {
+            return Err(Error::other(format!(
+                "loan term must be between 1 and {} months",
+                MAX_TERM_MONTHS
+            )));
+        }
+
+        let loan = Loan {
+            id,
+            customer_id,
+            principal,
+            currency: String::from(BASE_CURRENCY),
+            annual_rate_bps,
+            term_months,
+            start,
+            outstanding: principal,
+            unpaid_interest: 0,
+            accrued_through: start,
+            repayments: Vec::new(),
+        };
+
+        // Refuses terms whose installments wouldn't fit
+        loan.schedule()?;
+
+        Ok(loan)
+    }
+
+    pub fn outstanding(&self) -> i32 {
+        self.outstanding
+    }
+
+    pub fn repayments(&self) -> &Vec<Repayment> {
+        &self.repayments
+    }
+
+    pub fn is_paid_off(&self) -> bool {
+        self.outstanding == 0 && self.unpaid_interest == 0
+    }
+
+    fn monthly_rate(&self) -> f64 {
+        self.annual_rate_bps as f64 / 10_000.0 / 12.0
+    }
+
+    pub fn fixed_payment(&self) -> Result<i32, Error> {
+        let rate = self.monthly_rate();
+        let months = self.term_months as f64;
+
+        let payment = if rate == 0.0 {
+            (self.principal as f64 / months).ceil()
+        } else {
+            self.principal as f64 * rate / (1.0 - (1.0 + rate).powf(-months))
+        };
+
+        self.amount(payment, "monthly payment")
+    }
+
+    pub fn schedule(&self) -> Result<Vec<Installment>, Error> {
+        let rate = self.monthly_rate();
+        let payment = self.fixed_payment()? as i64;
+        let mut remaining = self.principal as i64;
+        let mut installments = Vec::new();
+
+        for number in 1..=self.term_months {
+            let interest = self.amount(remaining as f64 * rate, "interest")? as i64;
+            let mut principal = payment - interest;
+
+            if number == self.term_months || principal > remaining {
+                principal = remaining;
+            }
+
+            remaining -= principal;
+            let due = self
+                .start
+                .checked_add_months(Months::new(number))
+                .ok_or_else(|| {
+                    Error::other(format!("loan {} runs past the last date we know", self.id))
+                })?;
+
+            installments.push(Installment {
+                number,
+                due,
+                payment: self.amount((principal + interest) as f64, "installment")?,
+                interest: interest as i32,
+                principal: principal as i32,
+                remaining: remaining as i32,
+            });
+
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        Ok(installments)
+    }
+
+    // 'value' rounded to whole units, or an error naming
+    // 'what' if it doesn't fit an i32
+    fn amount(&self, value: f64, what: &str) -> Result<i32, Error> {
+        round_amount(value)
+            .ok_or_else(|| Error::other(format!("{} on loan {} doesn't fit an i32", what, self.id)))
+    }
+
+    fn interest_between(&self, from: NaiveDate, to: NaiveDate) -> Result<i32, Error> {
+        let days = (to - from).num_days().max(0) as f64;
+        let annual_rate = self.annual_rate_bps as f64 / 10_000.0;
+
+        self.amount(
+            self.outstanding as f64 * annual_rate * days / 365.0,
+            "interest",
+        )
+    }
+
+    // Everything it takes to close the loan on 'on'
+    pub fn payoff_amount(&self, on: NaiveDate) -> Result<i32, Error> {
+        let payoff = self.outstanding as i64
+            + self.unpaid_interest as i64
+            + self.interest_between(self.accrued_through, on)? as i64;
+
+        i32::try_from(payoff).map_err(|_| {
+            Error::other(format!(
+                "payoff of loan {} on {} doesn't fit an i32",
+                self.id, on
+            ))
+        })
+    }
+
+    pub fn repay(&mut self, amount: i32, on: NaiveDate) -> Result<Repayment, Error> {
+        self.check_repayment(amount, on)?;
+
+        // Fits: it's part of the payoff 'check_repayment'
+        // just worked out
+        self.unpaid_interest += self.interest_between(self.accrued_through, on)?;
+        self.accrued_through = on;
+
+        let interest = amount.min(self.unpaid_interest);
+        let principal = amount - interest;
+
+        self.unpaid_interest -= interest;
+        self.outstanding -= principal;
+
+        let repayment = Repayment {
+            on,
+            amount,
+            interest,
+            principal,
+        };
+        self.repayments.push(repayment.clone());
+
+        Ok(repayment)
+    }
+
+    // Everything 'repay' checks, without touching the loan
+    pub fn check_repayment(&self, amount: i32, on: NaiveDate) -> Result<(), Error> {
+        if amount <= 0 {
+            return Err(Error::other("repayment amount must be positive"));
+        }
+
+        if on < self.accrued_through {
+            return Err(Error::other(format!(
+                "loan {} already has activity after {}",
+                self.id, on
+            )));
+        }
+
+        let payoff = self.payoff_amount(on)?;
+        if amount > payoff {
+            return Err(Error::other(format!(
+                "loan {} can be paid off with {}, got {}",
+                self.id, payoff, amount
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Compares what the schedule says should have been paid
+    // by 'as_of' with what actually was
+    pub fn arrears(&self, as_of: NaiveDate) -> Result<Arrears, Error> {
+        let no_arrears = Arrears {
+            missed_installments: 0,
+            amount_overdue: 0,
+        };
+
+        if self.is_paid_off() {
+            return Ok(no_arrears);
+        }
+
+        let paid: i64 = self
+            .repayments
+            .iter()
+            .filter(|repayment| repayment.on <= as_of)
+            .map(|repayment| repayment.amount as i64)
+            .sum();
+
+        let mut due: i64 = 0;
+        let mut missed_installments = 0;
+
+        for installment in self.schedule()? {
+            if installment.due > as_of {
+                break;
+            }
+
+            due += installment.payment as i64;
+            if due > paid {
+                missed_installments += 1;
+            }
+        }
+
+        if due <= paid {
+            return Ok(no_arrears);
+        }
+
+        Ok(Arrears {
+            missed_installments,
+            amount_overdue: due - paid,
+        })
+    }
+}
+
+// Inherent implementation
+impl Bank {
+    pub fn loan(&self, id: u32) -> Option<&Loan> {
+        self.loans.iter().find(|loan| loan.id == id)
+    }
+
+    // Pays the principal out into one of the customer's
+    // accounts
+    pub(super) fn disburse_loan(
+        &mut self,
+        loan: Loan,
+        account_id: u32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        if self.loan(loan.id).is_some() {
+            return Err(Error::other(format!("loan {} already exists", loan.id)));
+        }
+
+        match self.account(account_id) {
+            Some(account) if account.role_of(loan.customer_id).is_none() => {
+                return Err(Error::other(format!(
+                    "customer {} doesn't hold account {}",
+                    loan.customer_id, account_id
+                )))
+            }
+            Some(account) if account.currency != loan.currency => {
+                return Err(Error::other(format!(
+                    "loan {} is in {}, account {} in {}",
+                    loan.id, loan.currency, account_id, account.currency
+                )))
+            }
+            Some(account) => account.check_deposit(loan.principal, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.screen(account_id, TransactionKind::Deposit, loan.principal, at)?;
+
+        self.apply_disbursal(loan, account_id, at)
+    }
+
+    // The money side of 'disburse_loan', once the loan and
+    // account have been vetted and screened; replaying a
+    // 'LoanDisbursed' event lands here
+    pub(super) fn apply_disbursal(
+        &mut self,
+        loan: Loan,
+        account_id: u32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        if self.loan(loan.id).is_some() {
+            return Err(Error::other(format!("loan {} already exists", loan.id)));
+        }
+
+        match self.account_mut(account_id) {
+            Some(account) => account.deposit_at(loan.principal, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        // The bank is now owed the principal, and owes the
+        // same amount to the account holder
+        self.ledger.post(
+            at,
+            format!("Loan {} paid out to account {}", loan.id, account_id),
+            vec![
+                JournalLine::debit(LOANS_RECEIVABLE, loan.principal),
+                JournalLine::credit(&customer_deposits_code(account_id), loan.principal),
+            ],
+        )?;
+        self.record_event(
+            at,
+            BankEvent::LoanDisbursed {
+                loan: loan.clone(),
+                account_id,
+            },
+        );
+
+        self.loans.push(loan);
+        Ok(())
+    }
+
+    // Takes a repayment out of 'account_id'
+    pub(super) fn repay_loan(
+        &mut self,
+        loan_id: u32,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<Repayment, Error> {
+        let on = at.date_naive();
+
+        // Both sides are vetted before either one changes,
+        // so money never leaves the account without paying
+        // anything off
+        let loan = match self.loan(loan_id) {
+            Some(loan) => loan,
+            None => return Err(Error::other(format!("unknown loan {}", loan_id))),
+        };
+        loan.check_repayment(amount, on)?;
+
+        // Only the borrower's own money, and only from an
+        // account they can take money out of
+        match self.account(account_id) {
+            Some(account) => {
+                match account.role_of(loan.customer_id) {
+                    Some(HolderRole::Owner | HolderRole::AuthorizedSigner) => {}
+                    _ => {
+                        return Err(Error::other(format!(
+                            "customer {} can't repay loan {} from account {}",
+                            loan.customer_id, loan_id, account_id
+                        )))
+                    }
+                }
+                if account.currency != loan.currency {
+                    return Err(Error::other(format!(
+                        "loan {} is in {}, account {} in {}",
+                        loan_id, loan.currency, account_id, account.currency
+                    )));
+                }
+                account.check_withdrawal(amount, at)?;
+            }
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.screen(account_id, TransactionKind::Withdrawal, amount, at)?;
+
+        self.apply_repayment(loan_id, account_id, amount, at)
+    }
+
+    // The money side of 'repay_loan'; replaying a
+    // 'LoanRepaid' event lands here
+    pub(super) fn apply_repayment(
+        &mut self,
+        loan_id: u32,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<Repayment, Error> {
+        let on = at.date_naive();
+        match self.loan(loan_id) {
+            Some(loan) => loan.check_repayment(amount, on)?,
+            None => return Err(Error::other(format!("unknown loan {}", loan_id))),
+        }
+
+        match self.account_mut(account_id) {
+            Some(account) => account.withdraw_at(amount, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        let repayment = match self.loans.iter_mut().find(|loan| loan.id == loan_id) {
+            Some(loan) => loan.repay(amount, on)?,
+            None => return Err(Error::other(format!("unknown loan {}", loan_id))),
+        };
+
+        let mut lines = vec![JournalLine::debit(
+            &customer_deposits_code(account_id),
+            amount,
+        )];
+        if repayment.principal > 0 {
+            lines.push(JournalLine::credit(LOANS_RECEIVABLE, repayment.principal));
+        }
+        if repayment.interest > 0 {
+            lines.push(JournalLine::credit(INTEREST_INCOME, repayment.interest));
+        }
+
+        self.ledger.post(
+            at,
+            format!("Repayment of loan {} from account {}", loan_id, account_id),
+            lines,
+        )?;
+        self.record_event(
+            at,
+            BankEvent::LoanRepaid {
+                loan_id,
+                account_id,
+                amount,
+            },
+        );
+
+        Ok(repayment)
+    }
+
+    pub fn loans_in_arrears(&self, as_of: NaiveDate) -> Result<Vec<(&Loan, Arrears)>, Error> {
+        let mut in_arrears = Vec::new();
+
+        for loan in &self.loans {
+            let arrears = loan.arrears(as_of)?;
+            if arrears.amount_overdue > 0 {
+                in_arrears.push((loan, arrears));
+            }
+        }
+
+        Ok(in_arrears)
+    }
+}
@@ -0,0 +1,720 @@
+use std::io::Error;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::account::Account;
+use super::currency::ExchangeRates;
+use super::customer::{Customer, HolderRole};
+use super::fraud::RuleEngine;
+use super::ledger::{
+    customer_deposits_code, GeneralLedger, JournalLine, LedgerAccount, LedgerAccountKind, CASH,
+    CUSTOMER_DEPOSITS, FX_CLEARING,
+};
+use super::report::ReconciliationReport;
+use super::rule::{Decision, ProposedTransaction};
+use super::status::AccountStatus;
+use super::transaction::TransactionKind;
+
+/*
+    ### Front door ###
+        - Anything that changes an account goes through
+        'Bank::execute', which authorizes and audits it
+        - The operations behind it (deposit_at, place_hold,
+        repay_loan, ...) are private to this module and the
+        ones below it, so 'main' can't call them directly;
+        only 'execute' and replaying events can
+*/
+
+pub mod auth;
+pub mod event;
+pub mod hold;
+pub mod import;
+pub mod loan;
+
+use auth::AuditLog;
+use event::{BankEvent, RecordedEvent};
+use loan::Loan;
+
+#[derive(Debug)]
+pub struct Bank {
+    // Only changed through 'execute'; read through
+    // 'account' and 'accounts'
+    accounts: Vec<Account>,
+    pub customers: Vec<Customer>,
+    // Posted to through 'execute' like everything else
+    ledger: GeneralLedger,
+    pub rules: RuleEngine,
+    pub exchange_rates: ExchangeRates,
+    loans: Vec<Loan>,
+    audit_log: AuditLog,
+    // How long card holds last before they lapse
+    pub hold_expiry: Duration,
+    events: Vec<RecordedEvent>,
+}
+
+// Everything a batch of operations can change, so the
+// batch can be undone; the fraud and audit logs are
+// deliberately left out - attempts stay on record
+#[derive(Debug)]
+struct Snapshot {
+    accounts: Vec<Account>,
+    ledger: GeneralLedger,
+    events: Vec<RecordedEvent>,
+}
+
+// Inherent implementation
+impl Bank {
+    pub fn new() -> Self {
+        Bank {
+            accounts: Vec::new(), // Can also use 'vec![]'
+            customers: Vec::new(),
+            ledger: GeneralLedger::new(),
+            rules: RuleEngine::new(),
+            exchange_rates: ExchangeRates::new(),
+            loans: Vec::new(),
+            audit_log: AuditLog::new(),
+            hold_expiry: Duration::days(7),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn add_customer(&mut self, customer: Customer) -> Result<(), Error> {
+        if self.customer(customer.id).is_some() {
+            return Err(Error::other(format!(
+                "customer {} already exists",
+                customer.id
+            )));
+        }
+
+        self.customers.push(customer);
+        Ok(())
+    }
+
+    pub fn customer(&self, id: u32) -> Option<&Customer> {
+        self.customers.iter().find(|customer| customer.id == id)
+    }
+
+    pub fn events(&self) -> &Vec<RecordedEvent> {
+        &self.events
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    pub fn ledger(&self) -> &GeneralLedger {
+        &self.ledger
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            accounts: self.accounts.clone(),
+            ledger: self.ledger.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.accounts = snapshot.accounts;
+        self.ledger = snapshot.ledger;
+        self.events = snapshot.events;
+    }
+
+    // Anything that moves money in or out of an account has
+    // to end up in the stream, wherever it lives
+    fn record_event(&mut self, at: DateTime<Utc>, event: BankEvent) {
+        self.events.push(RecordedEvent {
+            sequence: self.events.len() as u64 + 1,
+            at,
+            event,
+        });
+    }
+
+    pub fn add_account(&mut self, account: Account) -> Result<(), Error> {
+        for holder in &account.holders {
+            if self.customer(holder.customer_id).is_none() {
+                return Err(Error::other(format!(
+                    "unknown customer {}",
+                    holder.customer_id
+                )));
+            }
+        }
+
+        self.open_account(account)
+    }
+
+    // 'add_account' without the customer checks; replaying
+    // events rebuilds accounts for customers the replayed
+    // bank has never heard of
+    fn open_account(&mut self, account: Account) -> Result<(), Error> {
+        if self.account(account.id).is_some() {
+            return Err(Error::other(format!(
+                "account {} already exists",
+                account.id
+            )));
+        }
+
+        let code = customer_deposits_code(account.id);
+        self.ledger.add_account(LedgerAccount {
+            code: code.clone(),
+            name: format!("Customer deposits - account {}", account.id),
+            kind: LedgerAccountKind::Liability,
+        })?;
+
+        // The owner is whoever opened the account
+        let owner_id = account
+            .holders
+            .iter()
+            .find(|holder| holder.role == HolderRole::Owner)
+            .map(|holder| holder.customer_id)
+            .unwrap_or_default();
+
+        self.record_event(
+            account.opened_at,
+            BankEvent::AccountOpened {
+                account_id: account.id,
+                owner_id,
+                currency: account.currency.clone(),
+            },
+        );
+
+        // Anything that happened before the account joined
+        // the bank becomes part of the stream and the books
+        // as well, exactly as if it had happened here
+        let mut history = Vec::new();
+
+        for holder in &account.holders {
+            if holder.customer_id != owner_id {
+                history.push((
+                    account.opened_at,
+                    BankEvent::HolderAdded {
+                        account_id: account.id,
+                        customer_id: holder.customer_id,
+                        role: holder.role,
+                    },
+                ));
+            }
+        }
+
+        for transaction in account.transactions() {
+            let event = match transaction.kind {
+                TransactionKind::Deposit => BankEvent::Deposited {
+                    account_id: account.id,
+                    amount: transaction.amount,
+                },
+                TransactionKind::Withdrawal => BankEvent::Withdrawn {
+                    account_id: account.id,
+                    amount: transaction.amount,
+                },
+            };
+            history.push((transaction.at, event));
+        }
+
+        for change in account.status_history() {
+            history.push((
+                change.at,
+                BankEvent::StatusChanged {
+                    account_id: account.id,
+                    to: change.to,
+                    reason: change.reason.clone(),
+                },
+            ));
+        }
+
+        for change in account.overdraft_history() {
+            history.push((
+                change.at,
+                BankEvent::OverdraftLimitChanged {
+                    account_id: account.id,
+                    limit: change.to,
+                },
+            ));
+        }
+
+        for hold in account.holds() {
+            history.push((
+                hold.placed_at,
+                BankEvent::HoldPlaced {
+                    account_id: account.id,
+                    hold_id: hold.id,
+                    amount: hold.amount,
+                    expires_at: hold.expires_at,
+                },
+            ));
+        }
+
+        // Stable, so changes at the same moment keep their
+        // order
+        history.sort_by_key(|(at, _)| *at);
+
+        for (at, event) in history {
+            match &event {
+                BankEvent::Deposited { amount, .. } => {
+                    self.post_deposit(account.id, *amount, at)?
+                }
+                BankEvent::Withdrawn { amount, .. } => {
+                    self.post_withdrawal(account.id, *amount, at)?
+                }
+                _ => {}
+            }
+            self.record_event(at, event);
+        }
+
+        self.accounts.push(account);
+        Ok(())
+    }
+
+    pub fn account(&self, id: u32) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.id == id)
+    }
+
+    pub fn accounts(&self) -> &Vec<Account> {
+        &self.accounts
+    }
+
+    fn account_mut(&mut self, id: u32) -> Option<&mut Account> {
+        self.accounts.iter_mut().find(|account| account.id == id)
+    }
+
+    pub fn add_holder(
+        &mut self,
+        account_id: u32,
+        customer_id: u32,
+        role: HolderRole,
+    ) -> Result<(), Error> {
+        if self.customer(customer_id).is_none() {
+            return Err(Error::other(format!("unknown customer {}", customer_id)));
+        }
+
+        match self.account_mut(account_id) {
+            Some(account) => account.add_holder(customer_id, role)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.record_event(
+            Utc::now(),
+            BankEvent::HolderAdded {
+                account_id,
+                customer_id,
+                role,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn remove_holder(&mut self, account_id: u32, customer_id: u32) -> Result<(), Error> {
+        match self.account_mut(account_id) {
+            Some(account) => account.remove_holder(customer_id)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.record_event(
+            Utc::now(),
+            BankEvent::HolderRemoved {
+                account_id,
+                customer_id,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn deposit_at(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        self.screen(account_id, TransactionKind::Deposit, amount, at)?;
+
+        let balance = match self.account_mut(account_id) {
+            Some(account) => account.deposit_at(amount, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.post_deposit(account_id, amount, at)?;
+        self.record_event(at, BankEvent::Deposited { account_id, amount });
+
+        Ok(balance)
+    }
+
+    fn withdraw_at(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        self.screen(account_id, TransactionKind::Withdrawal, amount, at)?;
+
+        let balance = match self.account_mut(account_id) {
+            Some(account) => account.withdraw_at(amount, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        self.post_withdrawal(account_id, amount, at)?;
+        self.record_event(at, BankEvent::Withdrawn { account_id, amount });
+
+        Ok(balance)
+    }
+
+    fn post_deposit(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        // Cash comes in, and the bank now owes it to the
+        // customer
+        self.ledger.post(
+            at,
+            format!("Deposit to account {}", account_id),
+            vec![
+                JournalLine::debit(CASH, amount),
+                JournalLine::credit(&customer_deposits_code(account_id), amount),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn post_withdrawal(
+        &mut self,
+        account_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        self.ledger.post(
+            at,
+            format!("Withdrawal from account {}", account_id),
+            vec![
+                JournalLine::debit(&customer_deposits_code(account_id), amount),
+                JournalLine::credit(CASH, amount),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn change_status(
+        &mut self,
+        account_id: u32,
+        to: AccountStatus,
+        reason: String,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        match self.account_mut(account_id) {
+            Some(account) => account.change_status_at(to, reason.clone(), at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.record_event(
+            at,
+            BankEvent::StatusChanged {
+                account_id,
+                to,
+                reason,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn set_overdraft_limit(
+        &mut self,
+        account_id: u32,
+        limit: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        match self.account_mut(account_id) {
+            Some(account) => account.set_overdraft_limit_at(limit, at)?,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        }
+
+        self.record_event(at, BankEvent::OverdraftLimitChanged { account_id, limit });
+
+        Ok(())
+    }
+
+    // Entries that never touch a customer account (fees,
+    // expenses, the owner's money); customer deposits only
+    // move along with the accounts they belong to
+    fn post_entry(
+        &mut self,
+        description: String,
+        lines: Vec<JournalLine>,
+        at: DateTime<Utc>,
+    ) -> Result<u32, Error> {
+        let customer_deposits = format!("{}-", CUSTOMER_DEPOSITS);
+        if let Some(line) = lines
+            .iter()
+            .find(|line| line.account_code.starts_with(&customer_deposits))
+        {
+            return Err(Error::other(format!(
+                "{} belongs to a customer account; move the money through the account instead",
+                line.account_code
+            )));
+        }
+
+        let id = self.ledger.post(at, description.clone(), lines.clone())?;
+        self.record_event(at, BankEvent::JournalPosted { description, lines });
+
+        Ok(id)
+    }
+
+    // 'amount' is in the source account's currency; returns
+    // what was credited to the target account, after
+    // conversion and spread
+    fn transfer_at(
+        &mut self,
+        from_id: u32,
+        to_id: u32,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<i32, Error> {
+        if from_id == to_id {
+            return Err(Error::other("can't transfer to the same account"));
+        }
+
+        let (from_currency, to_currency) = match (self.account(from_id), self.account(to_id)) {
+            (Some(from), Some(to)) => {
+                // Checked up front so money never leaves the
+                // source account with nowhere to go
+                if !to.status().accepts_deposits() {
+                    return Err(Error::other(format!(
+                        "account {} is {:?} and can't accept deposits",
+                        to.id,
+                        to.status()
+                    )));
+                }
+
+                (from.currency.clone(), to.currency.clone())
+            }
+            (None, _) => return Err(Error::other(format!("unknown account {}", from_id))),
+            (_, None) => return Err(Error::other(format!("unknown account {}", to_id))),
+        };
+
+        let credited =
+            self.exchange_rates
+                .convert_with_spread(amount, &from_currency, &to_currency)?;
+        if credited <= 0 {
+            return Err(Error::other(format!(
+                "{} {} is too small to transfer",
+                amount, from_currency
+            )));
+        }
+
+        self.screen(from_id, TransactionKind::Withdrawal, amount, at)?;
+        self.screen(to_id, TransactionKind::Deposit, credited, at)?;
+
+        self.apply_transfer(from_id, to_id, amount, credited, at)?;
+
+        Ok(credited)
+    }
+
+    // Moves money that has already been converted and
+    // screened; replaying a 'Transferred' event lands here
+    fn apply_transfer(
+        &mut self,
+        from_id: u32,
+        to_id: u32,
+        amount: i32,
+        credited: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        // Both legs are vetted before either one runs, so a
+        // transfer never debits the source and then fails
+        // to credit the target
+        let (from_currency, to_currency) = match (self.account(from_id), self.account(to_id)) {
+            (Some(from), Some(to)) => {
+                from.check_withdrawal(amount, at)?;
+                to.check_deposit(credited, at)?;
+                (from.currency.clone(), to.currency.clone())
+            }
+            (None, _) => return Err(Error::other(format!("unknown account {}", from_id))),
+            (_, None) => return Err(Error::other(format!("unknown account {}", to_id))),
+        };
+
+        if let Some(from) = self.account_mut(from_id) {
+            from.withdraw_at(amount, at)?;
+        }
+        if let Some(to) = self.account_mut(to_id) {
+            to.deposit_at(credited, at)?;
+        }
+
+        let description = format!("Transfer from account {} to {}", from_id, to_id);
+        let from_code = customer_deposits_code(from_id);
+        let to_code = customer_deposits_code(to_id);
+
+        if from_currency == to_currency {
+            self.ledger.post(
+                at,
+                description,
+                vec![
+                    JournalLine::debit(&from_code, amount),
+                    JournalLine::credit(&to_code, amount),
+                ],
+            )?;
+        } else {
+            self.ledger.post(
+                at,
+                format!("{} ({} leg)", description, from_currency),
+                vec![
+                    JournalLine::debit(&from_code, amount),
+                    JournalLine::credit(FX_CLEARING, amount),
+                ],
+            )?;
+            self.ledger.post(
+                at,
+                format!("{} ({} leg)", description, to_currency),
+                vec![
+                    JournalLine::debit(FX_CLEARING, credited),
+                    JournalLine::credit(&to_code, credited),
+                ],
+            )?;
+        }
+
+        self.record_event(
+            at,
+            BankEvent::Transferred {
+                from_id,
+                to_id,
+                amount,
+                credited,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Runs the fraud rules; flagged transactions still go
+    // through, denied ones never reach the account
+    fn screen(
+        &mut self,
+        account_id: u32,
+        kind: TransactionKind,
+        amount: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let account = match self
+            .accounts
+            .iter()
+            .find(|account| account.id == account_id)
+        {
+            Some(account) => account,
+            None => return Err(Error::other(format!("unknown account {}", account_id))),
+        };
+
+        let transaction = ProposedTransaction {
+            account_id,
+            kind,
+            amount,
+            at,
+        };
+
+        match self.rules.evaluate(account, &transaction) {
+            Decision::Deny(reason) => Err(Error::other(format!("denied by {}", reason))),
+            Decision::Allow | Decision::Flag(..) => Ok(()),
+        }
+    }
+
+    // Every account the customer holds, whatever their role
+    pub fn accounts_for_customer(&self, customer_id: u32) -> Vec<&Account> {
+        self.accounts
+            .iter()
+            .filter(|account| account.role_of(customer_id).is_some())
+            .collect()
+    }
+
+    // Adds up raw amounts whatever their currency; only
+    // meaningful when every account uses the same one
+    pub fn total_balance(&self) -> i64 {
+        // ### Method 1 ###
+        // let mut total = 0;
+        // for acc in &self.accounts {
+        //     total += acc.balance;
+        // }
+        // total
+
+        // ### Method 2 ###
+        self.accounts
+            .iter()
+            .map(|account| account.balance() as i64)
+            .sum()
+    }
+
+    // Every account converted at the mid-market rate
+    pub fn total_balance_in(&self, currency: &str) -> Result<i32, Error> {
+        // Added up as i64, since a running i32 total can
+        // overflow on the way even when the result fits
+        let mut total: i64 = 0;
+
+        for account in &self.accounts {
+            total += self
+                .exchange_rates
+                .convert(account.balance(), &account.currency, currency)?
+                as i64;
+        }
+
+        i32::try_from(total).map_err(|_| {
+            Error::other(format!(
+                "total balance in {} doesn't fit in an i32",
+                currency
+            ))
+        })
+    }
+
+    // What the accounts hold minus what the ledger says the
+    // bank owes its customers; anything but 0 means some
+    // balance changed without going through the books
+    pub fn ledger_discrepancy(&self) -> i64 {
+        let owed_to_customers: i64 = self
+            .accounts
+            .iter()
+            .map(|account| -self.ledger.net_debit(&customer_deposits_code(account.id)))
+            .sum();
+
+        self.total_balance() - owed_to_customers
+    }
+
+    pub fn summary(&self) -> Vec<String> {
+        // ### Method 1 ###
+        // let mut summary = Vec::new();
+        // for acc in &self.accounts {
+        //     summary.push(acc.summary());
+        // }
+        // summary
+
+        // ### Method 2 ###
+        self.accounts
+            .iter()
+            .map(|account| format!("{} - {}", self.holder_names(account), account.summary()))
+            .collect::<Vec<String>>()
+    }
+
+    // Compares what every account has stored against what
+    // its ledger says it should have
+    pub fn reconcile(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> ReconciliationReport {
+        ReconciliationReport {
+            from,
+            to,
+            lines: self
+                .accounts
+                .iter()
+                .map(|account| account.reconcile(from, to))
+                .collect(),
+        }
+    }
+
+    fn holder_names(&self, account: &Account) -> String {
+        account
+            .holders
+            .iter()
+            .map(|holder| match self.customer(holder.customer_id) {
+                Some(customer) => customer.name.clone(),
+                None => format!("Customer {}", holder.customer_id),
+            })
+            .collect::<Vec<String>>()
+            .join(" & ")
+    }
+}
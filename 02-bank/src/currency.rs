@@ -0,0 +1,169 @@
+use std::fs;
+use std::io::Error;
+
+// Amounts are whole units of whatever currency the account
+// is in; rounding happens once, after conversion
+pub const BASE_CURRENCY: &str = "USD";
+
+// Rounds a computed amount back to whole units; 'None' if
+// it isn't a number or doesn't fit an i32 (a cast would
+// quietly saturate instead)
+pub fn round_amount(value: f64) -> Option<i32> {
+    let rounded = value.round();
+    if !rounded.is_finite() {
+        return None;
+    }
+
+    i32::try_from(rounded as i64).ok()
+}
+
+#[derive(Debug, Clone)]
+pub struct ExchangeRate {
+    pub base: String,
+    pub quote: String,
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExchangeRates {
+    rates: Vec<ExchangeRate>,
+    // Charged on every cross-currency transfer, in basis
+    // points (100 bps = 1%)
+    pub spread_bps: u32,
+}
+
+// Inherent implementation
+impl ExchangeRates {
+    pub fn new() -> Self {
+        ExchangeRates {
+            rates: Vec::new(),
+            spread_bps: 0,
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    /*
+        ### Rates file ###
+            # base,quote,rate
+            USD,EUR,0.92
+
+            - Blank lines and lines starting with '#' are
+            skipped
+            - The inverse rate (EUR -> USD) is derived, so
+            each pair only needs to be listed once
+    */
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut rates = ExchangeRates::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if fields.len() != 3 {
+                return Err(Error::other(format!(
+                    "line {}: expected 'base,quote,rate'",
+                    index + 1
+                )));
+            }
+
+            let rate = match fields[2].parse::<f64>() {
+                Ok(rate) if rate > 0.0 => rate,
+                _ => {
+                    return Err(Error::other(format!(
+                        "line {}: invalid rate '{}'",
+                        index + 1,
+                        fields[2]
+                    )))
+                }
+            };
+
+            rates.set_rate(fields[0], fields[1], rate);
+        }
+
+        Ok(rates)
+    }
+
+    pub fn set_rate(&mut self, base: &str, quote: &str, rate: f64) {
+        let base = base.to_uppercase();
+        let quote = quote.to_uppercase();
+
+        self.rates
+            .retain(|existing| !(existing.base == base && existing.quote == quote));
+        self.rates.push(ExchangeRate { base, quote, rate });
+    }
+
+    // Direct, inverse, or through one common currency (EUR
+    // -> USD -> GBP)
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+
+        if let Some(rate) = self.direct_rate(from, to) {
+            return Some(rate);
+        }
+
+        self.rates
+            .iter()
+            .flat_map(|rate| [&rate.base, &rate.quote])
+            .find_map(|via| {
+                let first = self.direct_rate(from, via)?;
+                let second = self.direct_rate(via, to)?;
+                Some(first * second)
+            })
+    }
+
+    fn direct_rate(&self, from: &str, to: &str) -> Option<f64> {
+        self.rates.iter().find_map(|rate| {
+            if rate.base == from && rate.quote == to {
+                Some(rate.rate)
+            } else if rate.base == to && rate.quote == from {
+                Some(1.0 / rate.rate)
+            } else {
+                None
+            }
+        })
+    }
+
+    // Mid-market conversion, no spread
+    pub fn convert(&self, amount: i32, from: &str, to: &str) -> Result<i32, Error> {
+        match self.rate(from, to) {
+            Some(rate) => converted(amount as f64 * rate, amount, from, to),
+            None => Err(Error::other(format!(
+                "no exchange rate from {} to {}",
+                from, to
+            ))),
+        }
+    }
+
+    // What the customer actually receives once the bank
+    // takes its spread
+    pub fn convert_with_spread(&self, amount: i32, from: &str, to: &str) -> Result<i32, Error> {
+        if from == to {
+            return Ok(amount);
+        }
+
+        match self.rate(from, to) {
+            Some(rate) => {
+                let spread = 1.0 - self.spread_bps as f64 / 10_000.0;
+                converted(amount as f64 * rate * spread, amount, from, to)
+            }
+            None => Err(Error::other(format!(
+                "no exchange rate from {} to {}",
+                from, to
+            ))),
+        }
+    }
+}
+
+fn converted(value: f64, amount: i32, from: &str, to: &str) -> Result<i32, Error> {
+    round_amount(value)
+        .ok_or_else(|| Error::other(format!("{} {} is too much to hold in {}", amount, from, to)))
+}
@@ -0,0 +1,50 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KycStatus {
+    Pending,
+    Verified,
+    Rejected,
+}
+
+#[derive(Debug)]
+pub struct Contact {
+    pub email: String,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Customer {
+    pub id: u32,
+    pub name: String,
+    pub contact: Contact,
+    pub kyc_status: KycStatus,
+}
+
+// Inherent implementation
+impl Customer {
+    pub fn new(id: u32, name: String, email: String) -> Self {
+        Customer {
+            id,
+            name,
+            contact: Contact { email, phone: None },
+            // Every new customer starts out unverified
+            kyc_status: KycStatus::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HolderRole {
+    // Full control over the account
+    Owner,
+    // Can move money but can't change who holds the
+    // account
+    AuthorizedSigner,
+    // Read-only access
+    Viewer,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountHolder {
+    pub customer_id: u32,
+    pub role: HolderRole,
+}
@@ -0,0 +1,175 @@
+use chrono::Duration;
+
+use super::account::Account;
+use super::rule::{Decision, ProposedTransaction, Rule};
+use super::transaction::TransactionKind;
+
+// Caps how much can leave an account on a single (UTC) day
+#[derive(Debug)]
+pub struct DailyWithdrawalLimit {
+    pub limit: i32,
+}
+
+impl Rule for DailyWithdrawalLimit {
+    fn name(&self) -> String {
+        String::from("daily withdrawal limit")
+    }
+
+    fn evaluate(&self, account: &Account, transaction: &ProposedTransaction) -> Decision {
+        if transaction.kind != TransactionKind::Withdrawal {
+            return Decision::Allow;
+        }
+
+        let day = transaction.at.date_naive();
+        let withdrawn_today: i64 = account
+            .transactions()
+            .iter()
+            .filter(|past| past.kind == TransactionKind::Withdrawal && past.at.date_naive() == day)
+            .map(|past| past.amount as i64)
+            .sum();
+
+        if withdrawn_today + transaction.amount as i64 > self.limit as i64 {
+            Decision::Deny(format!(
+                "{} would be withdrawn on {}, the limit is {}",
+                withdrawn_today + transaction.amount as i64,
+                day,
+                self.limit
+            ))
+        } else {
+            Decision::Allow
+        }
+    }
+}
+
+// At most 'max_transactions' in any 'window_minutes' long
+// window, counting the proposed one
+#[derive(Debug)]
+pub struct Velocity {
+    pub max_transactions: usize,
+    pub window_minutes: i64,
+}
+
+impl Rule for Velocity {
+    fn name(&self) -> String {
+        String::from("velocity")
+    }
+
+    fn evaluate(&self, account: &Account, transaction: &ProposedTransaction) -> Decision {
+        let window_start = transaction.at - Duration::minutes(self.window_minutes);
+        let recent = account
+            .transactions()
+            .iter()
+            .filter(|past| past.at > window_start && past.at <= transaction.at)
+            .count();
+
+        if recent + 1 > self.max_transactions {
+            Decision::Deny(format!(
+                "{} transactions within {} minutes, at most {} allowed",
+                recent + 1,
+                self.window_minutes,
+                self.max_transactions
+            ))
+        } else {
+            Decision::Allow
+        }
+    }
+}
+
+// Flags amounts far above what the account usually sees;
+// accounts without enough history are left alone
+#[derive(Debug)]
+pub struct UnusualAmount {
+    pub multiplier: i32,
+    pub min_history: usize,
+}
+
+impl Rule for UnusualAmount {
+    fn name(&self) -> String {
+        String::from("unusual amount")
+    }
+
+    fn evaluate(&self, account: &Account, transaction: &ProposedTransaction) -> Decision {
+        let history: Vec<i32> = account
+            .transactions()
+            .iter()
+            .filter(|past| past.kind == transaction.kind)
+            .map(|past| past.amount)
+            .collect();
+
+        if history.len() < self.min_history {
+            return Decision::Allow;
+        }
+
+        let average =
+            history.iter().map(|amount| *amount as i64).sum::<i64>() / history.len() as i64;
+
+        if transaction.amount as i64 > average * self.multiplier as i64 {
+            Decision::Flag(format!(
+                "{} is more than {}x the usual {}",
+                transaction.amount, self.multiplier, average
+            ))
+        } else {
+            Decision::Allow
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DecisionRecord {
+    pub transaction: ProposedTransaction,
+    pub decision: Decision,
+}
+
+#[derive(Debug)]
+pub struct RuleEngine {
+    rules: Vec<Box<dyn Rule>>,
+    log: Vec<DecisionRecord>,
+}
+
+// Inherent implementation
+impl RuleEngine {
+    pub fn new() -> Self {
+        RuleEngine {
+            rules: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    pub fn log(&self) -> &Vec<DecisionRecord> {
+        &self.log
+    }
+
+    // Runs every rule; a single deny wins over any number
+    // of flags, and every decision ends up in the log
+    pub fn evaluate(&mut self, account: &Account, transaction: &ProposedTransaction) -> Decision {
+        let mut denials = Vec::new();
+        let mut flags = Vec::new();
+
+        for rule in &self.rules {
+            match rule.evaluate(account, transaction) {
+                Decision::Allow => {}
+                Decision::Flag(reason) => flags.push(format!("{}: {}", rule.name(), reason)),
+                Decision::Deny(reason) => denials.push(format!("{}: {}", rule.name(), reason)),
+            }
+        }
+
+        let decision = if !denials.is_empty() {
+            Decision::Deny(denials.join("; "))
+        } else if !flags.is_empty() {
+            Decision::Flag(flags.join("; "))
+        } else {
+            Decision::Allow
+        };
+
+        self.log.push(DecisionRecord {
+            transaction: transaction.clone(),
+            decision: decision.clone(),
+        });
+
+        decision
+    }
+}
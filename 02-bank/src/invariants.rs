@@ -0,0 +1,328 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use proptest::prelude::*;
+
+use super::account::Account;
+use super::bank::auth::{Actor, Operation};
+use super::bank::event::BankEvent;
+use super::bank::Bank;
+use super::currency::ExchangeRates;
+use super::customer::Customer;
+
+/*
+    ### Property tests ###
+        - proptest generates random sequences of bank
+        operations, including ones that should fail
+        (negative amounts, overdrawing, self-transfers)
+        - Some operations are back-dated, so their
+        timestamps don't always go up
+        - After every single operation the invariants below
+        must hold; when one doesn't, proptest shrinks the
+        sequence down to the smallest one that still breaks
+*/
+
+#[derive(Debug, Clone)]
+enum Op {
+    Open {
+        owner_id: u32,
+        overdraft_limit: i32,
+        // Minutes after 'start()'; operations dated before
+        // that must be refused
+        opens_after: i64,
+    },
+    Deposit {
+        account: usize,
+        amount: i32,
+    },
+    Withdraw {
+        account: usize,
+        amount: i32,
+    },
+    Transfer {
+        from: usize,
+        to: usize,
+        amount: i32,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1..=3u32, 0..200i32, prop_oneof![Just(0i64), 0..90i64]).prop_map(
+            |(owner_id, overdraft_limit, opens_after)| Op::Open {
+                owner_id,
+                overdraft_limit,
+                opens_after,
+            }
+        ),
+        // Now and then big enough to overflow a balance
+        (
+            any::<usize>(),
+            prop_oneof![-50..1000i32, i32::MAX - 1000..=i32::MAX]
+        )
+            .prop_map(|(account, amount)| Op::Deposit { account, amount }),
+        (any::<usize>(), -50..1000i32)
+            .prop_map(|(account, amount)| Op::Withdraw { account, amount }),
+        (any::<usize>(), any::<usize>(), -50..1000i32)
+            .prop_map(|(from, to, amount)| Op::Transfer { from, to, amount }),
+    ]
+}
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+}
+
+fn new_bank() -> Bank {
+    let mut bank = Bank::new();
+
+    for id in 1..=3 {
+        bank.add_customer(Customer::new(
+            id,
+            format!("Customer {}", id),
+            format!("customer{}@example.com", id),
+        ))
+        .unwrap();
+    }
+
+    bank
+}
+
+// Generated indexes can be anything; map them onto the
+// accounts that actually exist
+fn account_id(bank: &Bank, index: usize) -> Option<u32> {
+    let accounts = bank.accounts();
+    if accounts.is_empty() {
+        None
+    } else {
+        Some(accounts[index % accounts.len()].id)
+    }
+}
+
+// By account id; a replay opens accounts in the order
+// they took effect, not the order they were added in
+fn balances(bank: &Bank) -> Vec<(u32, i32)> {
+    let mut balances: Vec<(u32, i32)> = bank
+        .accounts()
+        .iter()
+        .map(|account| (account.id, account.balance()))
+        .collect();
+    balances.sort();
+    balances
+}
+
+// Everything about every account, holds, histories and
+// transactions included; replay opens accounts in time
+// order, so they're sorted by id
+fn accounts(bank: &Bank) -> Vec<String> {
+    let mut accounts: Vec<_> = bank.accounts().iter().collect();
+    accounts.sort_by_key(|account| account.id);
+    accounts
+        .iter()
+        .map(|account| format!("{:?}", account))
+        .collect()
+}
+
+// The books, sorted by when and what; entry ids follow the
+// order things were recorded in, so they're left out
+fn journal(bank: &Bank) -> Vec<(DateTime<Utc>, String, String)> {
+    let mut entries: Vec<_> = bank
+        .ledger()
+        .entries()
+        .iter()
+        .map(|entry| {
+            (
+                entry.at,
+                entry.description.clone(),
+                format!("{:?}", entry.lines),
+            )
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+// Applies 'op' and returns how much money entered (+) or
+// left (-) the bank, or 'None' if the bank refused it. An
+// admin may do anything, so only the bank's own checks
+// refuse operations
+fn apply(bank: &mut Bank, op: &Op, at: DateTime<Utc>) -> Option<i64> {
+    let admin = Actor::Admin(1);
+
+    match op {
+        Op::Open {
+            owner_id,
+            overdraft_limit,
+            opens_after,
+        } => {
+            let mut account = Account::new(bank.accounts().len() as u32 + 1, *owner_id);
+            account.opened_at = start() + Duration::minutes(*opens_after);
+            account
+                .set_overdraft_limit_at(*overdraft_limit, account.opened_at)
+                .ok()?;
+            bank.add_account(account).ok().map(|_| 0)
+        }
+        Op::Deposit { account, amount } => {
+            let id = account_id(bank, *account)?;
+            let operation = Operation::Deposit {
+                account_id: id,
+                amount: *amount,
+            };
+            bank.execute(admin, operation, at)
+                .ok()
+                .map(|_| *amount as i64)
+        }
+        Op::Withdraw { account, amount } => {
+            let id = account_id(bank, *account)?;
+            let operation = Operation::Withdraw {
+                account_id: id,
+                amount: *amount,
+            };
+            bank.execute(admin, operation, at)
+                .ok()
+                .map(|_| -*amount as i64)
+        }
+        Op::Transfer { from, to, amount } => {
+            let from_id = account_id(bank, *from)?;
+            let to_id = account_id(bank, *to)?;
+            let operation = Operation::Transfer {
+                from_id,
+                to_id,
+                amount: *amount,
+            };
+            bank.execute(admin, operation, at).ok().map(|_| 0)
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn banking_invariants_hold(
+        ops in prop::collection::vec((op(), prop_oneof![Just(0i64), 0..30i64]), 1..60)
+    ) {
+        let mut bank = new_bank();
+        let mut net_inflow = 0;
+
+        for (step, (op, back_dated_by)) in ops.iter().enumerate() {
+            let at = start() + Duration::minutes(step as i64 + 1 - back_dated_by);
+            let before = balances(&bank);
+
+            match apply(&mut bank, op, at) {
+                Some(inflow) => net_inflow += inflow,
+                // A refused operation leaves every balance alone
+                None => {
+                    let mut after = balances(&bank);
+                    after.truncate(before.len());
+                    prop_assert_eq!(&after, &before, "refused {:?} changed balances", op);
+                }
+            }
+
+            // Money is conserved: only deposits and
+            // withdrawals move the total
+            prop_assert_eq!(bank.total_balance(), net_inflow);
+
+            // 'total_balance' is the sum of the accounts
+            let sum: i64 = bank.accounts().iter().map(|account| account.balance() as i64).sum();
+            prop_assert_eq!(bank.total_balance(), sum);
+            match bank.total_balance_in("USD") {
+                Ok(total) => prop_assert_eq!(total as i64, sum),
+                // Only once the total no longer fits an i32
+                Err(..) => prop_assert!(sum > i32::MAX as i64 || sum < i32::MIN as i64),
+            }
+
+            for account in bank.accounts() {
+                // Never below the overdraft limit
+                prop_assert!(
+                    account.balance() >= -account.overdraft_limit(),
+                    "account {} at {} with limit {}",
+                    account.id,
+                    account.balance(),
+                    account.overdraft_limit()
+                );
+
+                // Stored balance always matches the ledger
+                prop_assert_eq!(account.balance() as i64, account.ledger_balance());
+            }
+
+            // The books balance and agree with the accounts
+            prop_assert_eq!(bank.ledger().trial_balance().difference(), 0);
+            prop_assert_eq!(bank.ledger_discrepancy(), 0);
+        }
+
+        // Replaying the event stream rebuilds the same bank
+        let replayed = bank.as_of(start() + Duration::days(1)).unwrap();
+        prop_assert_eq!(balances(&replayed), balances(&bank));
+        prop_assert_eq!(accounts(&replayed), accounts(&bank));
+        prop_assert_eq!(journal(&replayed), journal(&bank));
+
+        // ...and every point in its history, back-dated
+        // events included
+        for recorded in bank.events() {
+            let replayed = bank.as_of(recorded.at);
+            prop_assert!(replayed.is_ok(), "replay to {} failed: {:?}", recorded.at, replayed.err());
+        }
+    }
+}
+
+proptest! {
+    // A conversion is either exact to the unit or refused;
+    // it never saturates at the edge of an i32
+    #[test]
+    fn conversions_never_saturate(
+        amount in prop_oneof![any::<i32>(), -1000..1000i32],
+        rate in prop_oneof![0.0001..2.0f64, 100.0..1e6f64],
+        spread_bps in 0..500u32,
+    ) {
+        let mut rates = ExchangeRates::new();
+        rates.set_rate("USD", "JPY", rate);
+        rates.spread_bps = spread_bps;
+
+        let spread = 1.0 - spread_bps as f64 / 10_000.0;
+        for (converted, exact) in [
+            (rates.convert(amount, "USD", "JPY"), amount as f64 * rate),
+            (rates.convert_with_spread(amount, "USD", "JPY"), amount as f64 * rate * spread),
+        ] {
+            match converted {
+                Ok(converted) => prop_assert!((converted as f64 - exact).abs() <= 0.5),
+                Err(..) => prop_assert!(exact.round() > i32::MAX as f64 || exact.round() < i32::MIN as f64),
+            }
+        }
+    }
+}
+
+// Replays everything the demo puts the bank through, loans,
+// holds, status and overdraft changes and journal entries
+// included, and checks nothing comes out different
+#[test]
+fn demo_history_replays() {
+    let bank = super::bank_demo().unwrap();
+    let replayed = bank.as_of(Utc::now() + Duration::days(1)).unwrap();
+
+    assert_eq!(balances(&replayed), balances(&bank));
+    assert_eq!(accounts(&replayed), accounts(&bank));
+    assert_eq!(journal(&replayed), journal(&bank));
+    assert_eq!(replayed.ledger_discrepancy(), 0);
+
+    let mut loans = 0;
+    for recorded in bank.events() {
+        if let BankEvent::LoanDisbursed { loan, .. } = &recorded.event {
+            assert_eq!(
+                format!("{:?}", replayed.loan(loan.id)),
+                format!("{:?}", bank.loan(loan.id))
+            );
+            loans += 1;
+        }
+    }
+    assert!(loans > 0);
+
+    // The replayed bank records the same events again
+    let mut events: Vec<_> = bank.events().iter().collect();
+    events.sort_by_key(|recorded| recorded.at);
+    let events: Vec<_> = events
+        .iter()
+        .map(|recorded| format!("{} {:?}", recorded.at, recorded.event))
+        .collect();
+    let replayed_events: Vec<_> = replayed
+        .events()
+        .iter()
+        .map(|recorded| format!("{} {:?}", recorded.at, recorded.event))
+        .collect();
+    assert_eq!(replayed_events, events);
+}
@@ -0,0 +1,264 @@
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+// Chart of accounts every ledger starts out with
+pub const CASH: &str = "1000";
+pub const LOANS_RECEIVABLE: &str = "1200";
+pub const FX_CLEARING: &str = "1500";
+pub const CUSTOMER_DEPOSITS: &str = "2100";
+pub const OWNERS_EQUITY: &str = "3000";
+pub const FEE_INCOME: &str = "4000";
+pub const INTEREST_INCOME: &str = "4100";
+pub const OPERATING_EXPENSES: &str = "5000";
+
+/*
+    ### Double-entry ###
+        - Every journal entry moves money between at least
+        two ledger accounts
+        - Total debits must equal total credits, so the
+        books as a whole always add up to zero
+        - Assets and expenses grow with debits; liabilities,
+        equity and income grow with credits
+        - Money a customer keeps with the bank is a
+        liability - the bank owes it back to them
+        - Lines are i32 like account balances; anything
+        that adds lines up is i64 so it can't overflow
+        - Amounts are in the currency of whichever bank
+        account they concern; cross-currency transfers
+        pass through 'FX clearing' with one entry per
+        currency, so each entry still balances
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerAccountKind {
+    Asset,
+    Liability,
+    Equity,
+    Income,
+    Expense,
+}
+
+#[derive(Debug, Clone)]
+pub struct LedgerAccount {
+    pub code: String,
+    pub name: String,
+    pub kind: LedgerAccountKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalLine {
+    pub account_code: String,
+    pub debit: i32,
+    pub credit: i32,
+}
+
+// Inherent implementation
+impl JournalLine {
+    pub fn debit(account_code: &str, amount: i32) -> Self {
+        JournalLine {
+            account_code: account_code.to_string(),
+            debit: amount,
+            credit: 0,
+        }
+    }
+
+    pub fn credit(account_code: &str, amount: i32) -> Self {
+        JournalLine {
+            account_code: account_code.to_string(),
+            debit: 0,
+            credit: amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub id: u32,
+    pub at: DateTime<Utc>,
+    pub description: String,
+    pub lines: Vec<JournalLine>,
+}
+
+#[derive(Debug)]
+pub struct TrialBalanceRow {
+    pub code: String,
+    pub name: String,
+    pub kind: LedgerAccountKind,
+    pub debit: i64,
+    pub credit: i64,
+}
+
+#[derive(Debug)]
+pub struct TrialBalance {
+    pub rows: Vec<TrialBalanceRow>,
+    pub total_debits: i64,
+    pub total_credits: i64,
+}
+
+// Inherent implementation
+impl TrialBalance {
+    // Anything but 0 means the books are broken
+    pub fn difference(&self) -> i64 {
+        self.total_debits - self.total_credits
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneralLedger {
+    accounts: Vec<LedgerAccount>,
+    entries: Vec<JournalEntry>,
+}
+
+// Inherent implementation
+impl GeneralLedger {
+    pub fn new() -> Self {
+        let chart = [
+            (CASH, "Cash", LedgerAccountKind::Asset),
+            (
+                LOANS_RECEIVABLE,
+                "Loans receivable",
+                LedgerAccountKind::Asset,
+            ),
+            (FX_CLEARING, "FX clearing", LedgerAccountKind::Asset),
+            (
+                CUSTOMER_DEPOSITS,
+                "Customer deposits",
+                LedgerAccountKind::Liability,
+            ),
+            (OWNERS_EQUITY, "Owner's equity", LedgerAccountKind::Equity),
+            (FEE_INCOME, "Fee income", LedgerAccountKind::Income),
+            (
+                INTEREST_INCOME,
+                "Interest income",
+                LedgerAccountKind::Income,
+            ),
+            (
+                OPERATING_EXPENSES,
+                "Operating expenses",
+                LedgerAccountKind::Expense,
+            ),
+        ];
+
+        GeneralLedger {
+            accounts: chart
+                .iter()
+                .map(|(code, name, kind)| LedgerAccount {
+                    code: code.to_string(),
+                    name: name.to_string(),
+                    kind: *kind,
+                })
+                .collect(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &Vec<JournalEntry> {
+        &self.entries
+    }
+
+    pub fn account(&self, code: &str) -> Option<&LedgerAccount> {
+        self.accounts.iter().find(|account| account.code == code)
+    }
+
+    pub fn add_account(&mut self, account: LedgerAccount) -> Result<(), Error> {
+        if self.account(&account.code).is_some() {
+            return Err(Error::other(format!(
+                "ledger account {} already exists",
+                account.code
+            )));
+        }
+
+        self.accounts.push(account);
+        Ok(())
+    }
+
+    pub fn post(
+        &mut self,
+        at: DateTime<Utc>,
+        description: String,
+        lines: Vec<JournalLine>,
+    ) -> Result<u32, Error> {
+        if lines.len() < 2 {
+            return Err(Error::other("journal entries need at least two lines"));
+        }
+
+        for line in &lines {
+            if self.account(&line.account_code).is_none() {
+                return Err(Error::other(format!(
+                    "unknown ledger account {}",
+                    line.account_code
+                )));
+            }
+
+            // Exactly one side of a line carries an amount
+            if line.debit < 0 || line.credit < 0 || (line.debit > 0) == (line.credit > 0) {
+                return Err(Error::other(format!(
+                    "line for {} must have either a debit or a credit",
+                    line.account_code
+                )));
+            }
+        }
+
+        let debits: i64 = lines.iter().map(|line| line.debit as i64).sum();
+        let credits: i64 = lines.iter().map(|line| line.credit as i64).sum();
+
+        if debits != credits {
+            return Err(Error::other(format!(
+                "entry doesn't balance: debits {} != credits {}",
+                debits, credits
+            )));
+        }
+
+        let id = self.entries.len() as u32 + 1;
+        self.entries.push(JournalEntry {
+            id,
+            at,
+            description,
+            lines,
+        });
+
+        Ok(id)
+    }
+
+    // Debits minus credits; positive for accounts that
+    // grow with debits
+    pub fn net_debit(&self, code: &str) -> i64 {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.lines.iter())
+            .filter(|line| line.account_code == code)
+            .map(|line| line.debit as i64 - line.credit as i64)
+            .sum()
+    }
+
+    pub fn trial_balance(&self) -> TrialBalance {
+        let rows: Vec<TrialBalanceRow> = self
+            .accounts
+            .iter()
+            .map(|account| {
+                let net = self.net_debit(&account.code);
+
+                TrialBalanceRow {
+                    code: account.code.clone(),
+                    name: account.name.clone(),
+                    kind: account.kind,
+                    debit: net.max(0),
+                    credit: (-net).max(0),
+                }
+            })
+            .collect();
+
+        TrialBalance {
+            total_debits: rows.iter().map(|row| row.debit).sum(),
+            total_credits: rows.iter().map(|row| row.credit).sum(),
+            rows,
+        }
+    }
+}
+
+// Each bank account gets its own liability sub-account, e.g.
+// '2100-3' for account 3
+pub fn customer_deposits_code(account_id: u32) -> String {
+    format!("{}-{}", CUSTOMER_DEPOSITS, account_id)
+}
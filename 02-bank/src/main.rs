@@ -1,84 +1,32 @@
-#[derive(Debug)]
-struct Account {
-    balance: i32,
-    holder: String,
-    id: u32,
-}
-
-// Inherent implementation
-impl Account {
-    fn new(id: u32, holder: String) -> Self {
-        // Implicit return
-        Account {
-            balance: 0,
-            holder,
-            id,
-        }
-    }
-
-    fn summary(&self) -> String {
-        format!(
-            "{} (AccID: {}) has balance {}",
-            self.holder, self.id, self.balance
-        )
-    }
-
-    // TODO: What if 'amount' is negative?
-    fn deposit(&mut self, amount: i32) -> i32 {
-        self.balance += amount;
-        self.balance
-    }
-
-    fn withdraw(&mut self, amount: i32) -> i32 {
-        self.balance -= amount;
-        self.balance
-    }
-}
-
-#[derive(Debug)]
-struct Bank {
-    accounts: Vec<Account>,
-}
-
-// Inherent implementation
-impl Bank {
-    fn new() -> Self {
-        Bank {
-            accounts: Vec::new(), // Can also use 'vec![]'
-        }
-    }
-
-    fn add_account(&mut self, account: Account) {
-        self.accounts.push(account);
-    }
-
-    fn total_balance(&self) -> i32 {
-        // ### Method 1 ###
-        // let mut total = 0;
-        // for acc in &self.accounts {
-        //     total += acc.balance;
-        // }
-        // total
-
-        // ### Method 2 ###
-        self.accounts.iter().map(|account| account.balance).sum()
-    }
-
-    fn summary(&self) -> Vec<String> {
-        // ### Method 1 ###
-        // let mut summary = Vec::new();
-        // for acc in &self.accounts {
-        //     summary.push(acc.summary());
-        // }
-        // summary
-
-        // ### Method 2 ###
-        self.accounts
-            .iter()
-            .map(|account| account.summary())
-            .collect::<Vec<String>>()
-    }
-}
+mod account;
+mod bank;
+mod currency;
+mod customer;
+mod fraud;
+#[cfg(test)]
+mod invariants;
+mod ledger;
+mod report;
+mod rule;
+mod status;
+mod transaction;
+
+use std::io::Error;
+
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+use account::Account;
+use bank::auth::{Actor, Operation, Outcome};
+use bank::import::ImportMode;
+use bank::loan::Loan;
+use bank::Bank;
+use currency::ExchangeRates;
+use customer::{Contact, Customer, HolderRole, KycStatus};
+use fraud::{DailyWithdrawalLimit, UnusualAmount, Velocity};
+use ledger::{
+    customer_deposits_code, JournalLine, CASH, FEE_INCOME, OPERATING_EXPENSES, OWNERS_EQUITY,
+};
+use rule::Decision;
 
 // This func accepts a reference
 fn print_account(account: &Account) {
@@ -95,14 +43,21 @@ fn print_account_list_without_ref(accounts: Vec<Account>) {
     println!("{:#?}", accounts);
 }
 
+// This func accepts a reference
+fn print_contact(contact: &Contact) {
+    println!("{:#?}", contact);
+}
+
 // This func accepts a mutable reference
 fn change_account(account: &mut Account) {
-    account.balance = 10;
+    if let Err(reason_deposit_failed) = account.deposit(10) {
+        println!("{}", reason_deposit_failed);
+    }
 }
 
 // NOTE: Try using &Account and return &account => !Error
 fn make_and_print_account() -> Account {
-    let account: Account = Account::new(1, String::from("John Davis"));
+    let account: Account = Account::new(1, 1);
 
     println!("{:#?}", account);
 
@@ -114,7 +69,7 @@ fn make_and_print_account() -> Account {
 }
 
 fn ref_demo_0() {
-    let account = Account::new(1, String::from("Johnny Wick"));
+    let account = Account::new(1, 1);
 
     // Multiple immutable references can co-exist for a
     // value
@@ -138,7 +93,7 @@ fn ref_demo_0() {
 
 fn ref_demo_1() {
     let bank = Bank::new();
-    let account = Account::new(1, String::from("Jamie Bravo"));
+    let account = Account::new(1, 1);
 
     // Immutable reference
     let account_ref = &account;
@@ -146,16 +101,30 @@ fn ref_demo_1() {
     print_account(account_ref);
 
     // Immutable reference
-    let bank_accounts_ref = &bank.accounts;
+    let bank_accounts_ref = bank.accounts();
 
     print_account_list_with_ref(bank_accounts_ref);
 
+    // Moves 'account' into the vector
+    print_account_list_without_ref(vec![account]);
+
+    let customer = Customer::new(
+        1,
+        String::from("John Wick"),
+        String::from("baba.yaga@continental.com"),
+    );
+
+    // Immutable reference to a field
+    let contact_ref = &customer.contact;
+
+    print_contact(contact_ref);
+
     /*
         ### QUESTION ###
             The rule is "You can't move a value while a ref
             to the value exists."; Reference to
-            bank.accounts exist & still we are able to move
-            it?
+            customer.contact exist & still we are able to
+            move it?
 
             ### Answer ###
             Rust allows you to borrow part of a struct
@@ -163,18 +132,20 @@ fn ref_demo_1() {
             out of the struct, as long as you DON'T USE THE
             REFERENCE AFTER THE MOVE.
     */
-    print_account_list_without_ref(bank.accounts);
+    let contact = customer.contact;
+
+    print_contact(&contact);
 
     // !Error: Can't use the reference once a value
-    // (bank.accounts) has been moved
-    // print_account_list_with_ref(bank_accounts_ref);
+    // (customer.contact) has been moved
+    // print_contact(contact_ref);
 
-    // !Error: borrow of partially moved value: `bank`
-    // println!("{:#?}", bank);
+    // !Error: borrow of partially moved value: `customer`
+    // println!("{:#?}", customer);
 }
 
 fn ref_demo_2() {
-    let mut account = Account::new(1, String::from("Betty Davis"));
+    let mut account = Account::new(1, 1);
 
     // !Error: We can't have a read-only (immutable)
     // reference if we have a mutable reference and
@@ -187,7 +158,7 @@ fn ref_demo_2() {
 
     // !Error: Can't modify a binding via its owner if we
     // have a reference (mutable or immutable)
-    // account.balance = 100;
+    // account.balance() = 100;
 
     // !Error: We can't have another mutable reference if
     // we already have one
@@ -224,36 +195,823 @@ fn demos() {
     lifetimes_demo_0();
 }
 
-fn main() {
-    demos();
+fn lifecycle_demo() -> Result<(), Error> {
+    let mut account = Account::new(4, 4);
+    account.deposit(50)?;
+
+    account.freeze(String::from("Suspicious cash deposits"))?;
+
+    // Frozen accounts still accept deposits...
+    account.deposit(25)?;
+
+    // ...but reject withdrawals
+    match account.withdraw(10) {
+        Ok(balance) => println!("Withdrew, balance is now {}", balance),
+        Err(reason_withdrawal_failed) => println!("{}", reason_withdrawal_failed),
+    }
+
+    account.activate(String::from("Source of funds verified"))?;
+    account.withdraw(75)?;
+
+    // Can't close an account that still holds money; here
+    // the balance is 0 so closing works
+    account.close(String::from("Customer moved to the Ozarks"))?;
+
+    if let Err(reason_deposit_failed) = account.deposit(10) {
+        println!("{}", reason_deposit_failed);
+    }
+
+    println!("{}", account.summary());
+    println!(
+        "History of account {} (now {:?}):",
+        account.id,
+        account.status()
+    );
+    for change in account.status_history() {
+        println!("  {:?} -> {:?}: {}", change.from, change.to, change.reason);
+    }
+    println!();
+
+    Ok(())
+}
+
+fn fraud_demo(bank: &mut Bank) -> Result<(), Error> {
+    bank.rules
+        .add_rule(Box::new(DailyWithdrawalLimit { limit: 150 }));
+    bank.rules.add_rule(Box::new(Velocity {
+        max_transactions: 3,
+        window_minutes: 10,
+    }));
+    bank.rules.add_rule(Box::new(UnusualAmount {
+        multiplier: 5,
+        min_history: 2,
+    }));
+
+    let at = |minute| Utc.with_ymd_and_hms(2024, 3, 10, 10, minute, 0).unwrap();
+    let owner = Actor::Customer(1);
+    let deposit = |amount| Operation::Deposit {
+        account_id: 1,
+        amount,
+    };
+    let withdraw = |amount| Operation::Withdraw {
+        account_id: 1,
+        amount,
+    };
+
+    // Way above the usual deposit - flagged, but it goes
+    // through
+    bank.execute(owner, deposit(2000), at(0))?;
+    bank.execute(owner, withdraw(100), at(1))?;
+
+    // Over the daily limit
+    if let Err(reason_withdrawal_denied) = bank.execute(owner, withdraw(100), at(2)) {
+        println!("{}", reason_withdrawal_denied);
+    }
+
+    bank.execute(owner, deposit(5), at(3))?;
+
+    // Too many transactions in 10 minutes
+    if let Err(reason_deposit_denied) = bank.execute(owner, deposit(5), at(4)) {
+        println!("{}", reason_deposit_denied);
+    }
+
+    for record in bank.rules.log() {
+        let transaction = &record.transaction;
+
+        let (outcome, reason) = match &record.decision {
+            Decision::Allow => continue,
+            Decision::Flag(reason) => ("flagged", reason),
+            Decision::Deny(reason) => ("denied", reason),
+        };
+
+        println!(
+            "  {} {:?} of {} on account {} {} - {}",
+            transaction.at.format("%H:%M"),
+            transaction.kind,
+            transaction.amount,
+            transaction.account_id,
+            outcome,
+            reason
+        );
+    }
+    println!();
+
+    Ok(())
+}
+
+fn currency_demo(bank: &mut Bank) -> Result<(), Error> {
+    bank.exchange_rates = ExchangeRates::from_file("rates.txt")?;
+    // 1.5%
+    bank.exchange_rates.spread_bps = 150;
+
+    let mut euro_account = Account::new(4, 2);
+    euro_account.currency = String::from("EUR");
+    let mut yen_account = Account::new(5, 3);
+    yen_account.currency = String::from("JPY");
+
+    bank.add_account(euro_account)?;
+    bank.add_account(yen_account)?;
+
+    // John McClane owns accounts 2 and 4
+    let transfer = |bank: &mut Bank, from_id, to_id, amount| -> Result<i32, Error> {
+        let operation = Operation::Transfer {
+            from_id,
+            to_id,
+            amount,
+        };
+        match bank.execute(Actor::Customer(2), operation, Utc::now())? {
+            Outcome::Transferred { credited } => Ok(credited),
+            _ => Err(Error::other("nothing was transferred")),
+        }
+    };
+
+    let credited = transfer(bank, 2, 4, 100)?;
+    println!(
+        "100 USD from account 2 arrived as {} EUR in account 4",
+        credited
+    );
+
+    // No direct EUR -> JPY rate; goes through USD
+    let credited = transfer(bank, 4, 5, 50)?;
+    println!(
+        "50 EUR from account 4 arrived as {} JPY in account 5",
+        credited
+    );
+
+    // 20 million dollars is more yen than an i32 can hold;
+    // refused rather than rounded down to i32::MAX
+    if let Err(reason_conversion_failed) = bank.exchange_rates.convert(20_000_000, "USD", "JPY") {
+        println!("{}", reason_conversion_failed);
+    }
+
+    for currency in ["USD", "EUR", "CHF"] {
+        match bank.total_balance_in(currency) {
+            Ok(total) => println!("Total balance in {}: {}", currency, total),
+            Err(reason_conversion_failed) => println!("{}", reason_conversion_failed),
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+fn event_demo(bank: &Bank) -> Result<(), Error> {
+    println!("{} events recorded", bank.events().len());
+    for recorded in bank.events().iter().take(3) {
+        println!(
+            "  #{} {} {:?}",
+            recorded.sequence,
+            recorded.at.format("%Y-%m-%d %H:%M"),
+            recorded.event
+        );
+    }
+
+    // End of day on March 1st
+    let march_1st = Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap();
+    for account_id in [2, 3] {
+        println!(
+            "Account {} had {} on March 1st",
+            account_id,
+            bank.balance_at(account_id, march_1st)?
+        );
+    }
+
+    // Replaying everything should land on today's balances;
+    // anything printed here is a bug
+    let replayed = bank.as_of(Utc::now())?;
+    for account in bank.accounts() {
+        if let Some(replayed_account) = replayed.account(account.id) {
+            if replayed_account.balance() != account.balance() {
+                println!(
+                    "Account {} holds {} but its events add up to {}",
+                    account.id,
+                    account.balance(),
+                    replayed_account.balance()
+                );
+            }
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+fn loan_demo(bank: &mut Bank) -> Result<(), Error> {
+    let day = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    let at = |month, day| Utc.with_ymd_and_hms(2024, month, day, 12, 0, 0).unwrap();
+
+    // 1200 at 6% a year over 12 months
+    let loan = Loan::new(1, 2, 1200, 600, 12, day(1, 15))?;
+
+    println!("Loan 1 pays {} a month:", loan.fixed_payment()?);
+    for installment in loan.schedule()?.iter().take(3) {
+        println!(
+            "  #{} due {}: {} = {} interest + {} principal, {} left",
+            installment.number,
+            installment.due,
+            installment.payment,
+            installment.interest,
+            installment.principal,
+            installment.remaining
+        );
+    }
 
+    // Only admins pay out loans; John McClane repays his
+    // own
+    let disburse = Operation::DisburseLoan {
+        loan,
+        account_id: 2,
+    };
+    bank.execute(Actor::Admin(1), disburse, at(1, 15))?;
+
+    let borrower = Actor::Customer(2);
+    let repay = |account_id, amount| Operation::RepayLoan {
+        loan_id: 1,
+        account_id,
+        amount,
+    };
+    bank.execute(borrower, repay(2, 103), at(2, 15))?;
+    bank.execute(borrower, repay(2, 103), at(3, 15))?;
+
+    // A back-dated repayment is refused before any money
+    // leaves the account
+    let balance = |bank: &Bank| bank.account(2).map(|account| account.balance());
+    let before = balance(bank);
+    if let Err(error) = bank.execute(borrower, repay(2, 103), at(2, 20)) {
+        println!(
+            "Refused: {}; balance {:?} -> {:?}",
+            error,
+            before,
+            balance(bank)
+        );
+    }
+
+    // Loan 1 is John McClane's; John Wick's account can't
+    // pay it off, even at John Wick's own request
+    if let Err(error) = bank.execute(Actor::Customer(1), repay(1, 103), at(3, 20)) {
+        println!("Refused: {}", error);
+    }
+
+    // Loans whose installments wouldn't fit an i32, or that
+    // run for centuries, are never made
+    for (principal, term_months) in [(i32::MAX, 1), (1200, 12_000)] {
+        if let Err(error) = Loan::new(2, 2, principal, 600, term_months, day(1, 15)) {
+            println!("Refused: {}", error);
+        }
+    }
+
+    // April and May are missed
+    for (loan, arrears) in bank.loans_in_arrears(day(6, 1))? {
+        println!(
+            "Loan {} of customer {} is {} behind ({} installments missed)",
+            loan.id, loan.customer_id, arrears.amount_overdue, arrears.missed_installments
+        );
+    }
+
+    // Paying it all off early. Repayments are screened like
+    // any withdrawal, so the daily limit of 150 stops it in
+    // one go; 150 a day it is
+    if let Some(loan) = bank.loan(1) {
+        let (outstanding, payoff) = (loan.outstanding(), loan.payoff_amount(day(6, 20))?);
+        if let Err(error) = bank.execute(borrower, repay(2, payoff), at(6, 20)) {
+            println!(
+                "Paying off loan 1 ({} principal left) with {} refused: {}",
+                outstanding, payoff, error
+            );
+        }
+    }
+
+    for on in 20..=30 {
+        let payoff = match bank.loan(1) {
+            Some(loan) if !loan.is_paid_off() => loan.payoff_amount(day(6, on))?,
+            _ => break,
+        };
+        bank.execute(borrower, repay(2, payoff.min(150)), at(6, on))?;
+    }
+
+    if let Some(loan) = bank.loan(1) {
+        for repayment in loan.repayments() {
+            println!(
+                "  {} paid {} ({} interest, {} principal)",
+                repayment.on, repayment.amount, repayment.interest, repayment.principal
+            );
+        }
+        println!("Paid off: {}\n", loan.is_paid_off());
+    }
+
+    Ok(())
+}
+
+fn auth_demo(bank: &mut Bank) -> Result<(), Error> {
+    // An hour apart, so the fraud rules from 'fraud_demo'
+    // don't get in the way (velocity)
+    let start = Utc::now();
+    let at = |hours| start + Duration::hours(hours);
+
+    // John Wick signs for account 2, Frank Castle holds
+    // nothing there
+    let attempts = [
+        (
+            Actor::Customer(1),
+            Operation::Deposit {
+                account_id: 2,
+                amount: 40,
+            },
+        ),
+        (
+            Actor::Customer(3),
+            Operation::Withdraw {
+                account_id: 2,
+                amount: 40,
+            },
+        ),
+        (
+            Actor::Customer(1),
+            Operation::CloseAccount {
+                account_id: 2,
+                reason: String::from("Signers can't close accounts"),
+            },
+        ),
+        (
+            Actor::Teller(7),
+            Operation::Transfer {
+                from_id: 2,
+                to_id: 4,
+                amount: 25,
+            },
+        ),
+        (
+            Actor::Auditor(9),
+            Operation::Withdraw {
+                account_id: 1,
+                amount: 5,
+            },
+        ),
+        (
+            Actor::Admin(1),
+            Operation::CloseAccount {
+                account_id: 2,
+                reason: String::from("Balance isn't 0 yet"),
+            },
+        ),
+        // Account 3 has been dormant since it joined the
+        // bank; only an admin can wake it up
+        (
+            Actor::Teller(7),
+            Operation::MarkDormant {
+                account_id: 3,
+                reason: String::from("Tellers can't change statuses"),
+            },
+        ),
+        (
+            Actor::Customer(3),
+            Operation::Activate {
+                account_id: 3,
+                reason: String::from("Owners can't either"),
+            },
+        ),
+        (
+            Actor::Admin(1),
+            Operation::Activate {
+                account_id: 3,
+                reason: String::from("Frank Castle is back in town"),
+            },
+        ),
+        (
+            Actor::Admin(1),
+            Operation::SetOverdraftLimit {
+                account_id: 4,
+                limit: 50,
+            },
+        ),
+        (
+            Actor::Admin(1),
+            Operation::Freeze {
+                account_id: 5,
+                reason: String::from("Where did all that yen come from?"),
+            },
+        ),
+    ];
+
+    for (hours, (actor, operation)) in (1..).zip(attempts) {
+        if let Err(reason_failed) = bank.execute(actor, operation, at(hours)) {
+            println!("{:?}: {}", actor, reason_failed);
+        }
+    }
+
+    // Everything else so far went through the front door
+    // too; only the attempts above are shown
+    println!("Audit log:");
+    for entry in bank
+        .audit_log()
+        .entries()
+        .iter()
+        .filter(|entry| entry.at > start)
+    {
+        println!(
+            "  {} {:?} {:?} -> {} {}",
+            entry.at.format("%H:%M"),
+            entry.actor,
+            entry.operation,
+            if entry.allowed { "allowed" } else { "DENIED" },
+            entry.reason
+        );
+    }
+    println!("{} denied attempts", bank.audit_log().denied().len());
+
+    if let Some(account) = bank.account(4) {
+        for change in account.overdraft_history() {
+            println!(
+                "  Account 4 overdraft limit {} -> {} at {}",
+                change.from,
+                change.to,
+                change.at.format("%H:%M")
+            );
+        }
+        println!("  Now {}\n", account.overdraft_limit());
+    }
+
+    Ok(())
+}
+
+fn hold_demo(bank: &mut Bank) -> Result<(), Error> {
+    bank.hold_expiry = Duration::days(3);
+
+    let at = |hour| Utc.with_ymd_and_hms(2024, 4, 1, hour, 0, 0).unwrap();
+    let print_balances = |bank: &Bank, hour| {
+        if let Some(account) = bank.account(1) {
+            println!(
+                "  balance {}, available {}, {} holds",
+                account.balance(),
+                account.available_balance(at(hour)),
+                account.holds().len()
+            );
+        }
+    };
+
+    // Holds go through the front door like any other
+    // withdrawal, so they're authorized and audited
+    let owner = Actor::Customer(1);
+    let place_hold = |bank: &mut Bank, amount, hour| -> Result<u32, Error> {
+        let operation = Operation::PlaceHold {
+            account_id: 1,
+            amount,
+        };
+        match bank.execute(owner, operation, at(hour))? {
+            Outcome::HoldPlaced { hold_id } => Ok(hold_id),
+            _ => Err(Error::other("no hold was placed")),
+        }
+    };
+
+    // Card payment of 30 that settles at 20
+    let hotel = place_hold(bank, 30, 9)?;
+    print_balances(bank, 9);
+    let capture = Operation::CaptureHold {
+        account_id: 1,
+        hold_id: hotel,
+        amount: 20,
+    };
+    bank.execute(owner, capture, at(10))?;
+    print_balances(bank, 10);
+
+    // Cancelled order
+    let shop = place_hold(bank, 15, 11)?;
+    let release = Operation::ReleaseHold {
+        account_id: 1,
+        hold_id: shop,
+    };
+    bank.execute(owner, release, at(11))?;
+    println!("  released hold {}", shop);
+
+    // Nobody ever captures this one
+    let parking = place_hold(bank, 10, 12)?;
+    print_balances(bank, 12);
+
+    // Someone who doesn't hold the account can't capture
+    let capture = Operation::CaptureHold {
+        account_id: 1,
+        hold_id: parking,
+        amount: 10,
+    };
+    if let Err(reason_denied) = bank.execute(Actor::Customer(3), capture, at(12)) {
+        println!("  {}", reason_denied);
+    }
+
+    if let Some(account) = bank.account(1) {
+        for hold in account.holds() {
+            println!(
+                "  hold {} of {} placed {} expires {}",
+                hold.id, hold.amount, hold.placed_at, hold.expires_at
+            );
+        }
+    }
+
+    let later = at(12) + Duration::days(4);
+    let capture = Operation::CaptureHold {
+        account_id: 1,
+        hold_id: parking,
+        amount: 10,
+    };
+    if let Err(reason_capture_failed) = bank.execute(owner, capture, later) {
+        println!("  {}", reason_capture_failed);
+    }
+    println!("  {} holds expired", bank.expire_holds(later));
+
+    let audited = bank
+        .audit_log()
+        .entries()
+        .iter()
+        .filter(|entry| {
+            matches!(
+                entry.operation,
+                Operation::PlaceHold { .. }
+                    | Operation::CaptureHold { .. }
+                    | Operation::ReleaseHold { .. }
+            )
+        })
+        .count();
+    println!("  {} hold operations in the audit log\n", audited);
+
+    Ok(())
+}
+
+fn ledger_demo(bank: &mut Bank) -> Result<(), Error> {
+    let now = Utc::now();
+    let post = |bank: &mut Bank, description: &str, lines| {
+        let operation = Operation::PostEntry {
+            description: String::from(description),
+            lines,
+        };
+        bank.execute(Actor::Admin(1), operation, now)
+    };
+
+    // Entries that never touch a customer account
+    post(
+        bank,
+        "Owner puts money into the bank",
+        vec![
+            JournalLine::debit(CASH, 1000),
+            JournalLine::credit(OWNERS_EQUITY, 1000),
+        ],
+    )?;
+    post(
+        bank,
+        "ATM fees collected",
+        vec![
+            JournalLine::debit(CASH, 15),
+            JournalLine::credit(FEE_INCOME, 15),
+        ],
+    )?;
+    post(
+        bank,
+        "Rent for the branch",
+        vec![
+            JournalLine::debit(OPERATING_EXPENSES, 200),
+            JournalLine::credit(CASH, 200),
+        ],
+    )?;
+
+    // !Error: debits and credits don't match
+    if let Err(reason_entry_rejected) = post(
+        bank,
+        "Money out of thin air",
+        vec![
+            JournalLine::debit(CASH, 500),
+            JournalLine::credit(OWNERS_EQUITY, 50),
+        ],
+    ) {
+        println!("{}", reason_entry_rejected);
+    }
+
+    // !Error: customer money only moves with the account,
+    // so the books and the accounts can't drift apart
+    if let Err(reason_entry_rejected) = post(
+        bank,
+        "Sneaky deposit",
+        vec![
+            JournalLine::debit(CASH, 10),
+            JournalLine::credit(&customer_deposits_code(3), 10),
+        ],
+    ) {
+        println!("{}", reason_entry_rejected);
+    }
+
+    println!("Journal:");
+    for entry in bank.ledger().entries() {
+        println!(
+            "  #{} {} {} ({} lines)",
+            entry.id,
+            entry.at.format("%Y-%m-%d"),
+            entry.description,
+            entry.lines.len()
+        );
+    }
+
+    let trial_balance = bank.ledger().trial_balance();
+    println!("Trial balance:");
+    for row in &trial_balance.rows {
+        println!(
+            "  {:<8} {:<32} {:<10} {:>8} {:>8}",
+            row.code,
+            row.name,
+            format!("{:?}", row.kind),
+            row.debit,
+            row.credit
+        );
+    }
+    println!(
+        "  Totals: {} / {} (difference {})",
+        trial_balance.total_debits,
+        trial_balance.total_credits,
+        trial_balance.difference()
+    );
+    println!(
+        "Accounts vs. ledger discrepancy: {}\n",
+        bank.ledger_discrepancy()
+    );
+
+    Ok(())
+}
+
+// Everything the bank goes through in the demos; also
+// replayed by the tests
+fn bank_demo() -> Result<Bank, Error> {
     let mut bank = Bank::new();
 
+    // Customers
+    let mut john_wick = Customer::new(
+        1,
+        String::from("John Wick"),
+        String::from("baba.yaga@continental.com"),
+    );
+    john_wick.contact.phone = Some(String::from("555-0100"));
+    john_wick.kyc_status = KycStatus::Verified;
+
+    bank.add_customer(john_wick)?;
+    bank.add_customer(Customer::new(
+        2,
+        String::from("John McClane"),
+        String::from("mcclane@nypd.gov"),
+    ))?;
+
+    let mut frank_castle = Customer::new(
+        3,
+        String::from("Frank Castle"),
+        String::from("punisher@hellskitchen.com"),
+    );
+    frank_castle.kyc_status = KycStatus::Rejected;
+
+    bank.add_customer(frank_castle)?;
+
     // Accounts
-    let mut account_1 = Account::new(1, String::from("John Wick"));
-    let mut account_2 = Account::new(2, String::from("John McClane"));
-    let mut account_3 = Account::new(3, String::from("Frank Castle"));
+    let mut account_1 = Account::new(1, 1);
+    let mut account_2 = Account::new(2, 2);
+    let mut account_3 = Account::new(3, 3);
+
+    // These accounts have been around since the start of
+    // 2024, so back-dated activity is allowed
+    let opened_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    account_1.opened_at = opened_at;
+    account_2.opened_at = opened_at;
+    account_3.opened_at = opened_at;
 
     // Ops on account_1
-    account_1.deposit(100);
-    account_1.withdraw(80);
+    account_1.deposit(100)?;
+    account_1.withdraw(80)?;
     println!("{}\n", account_1.summary());
 
     // Deposit to account_2
-    account_2.deposit(10);
+    account_2.deposit(10)?;
 
     // Deposit to account_3
-    account_3.deposit(30);
+    account_3.deposit(30)?;
+    account_3.mark_dormant(String::from("No activity for 12 months"))?;
 
     // Add accounts
-    bank.add_account(account_1);
-    bank.add_account(account_2);
-    bank.add_account(account_3);
+    bank.add_account(account_1)?;
+    bank.add_account(account_2)?;
+    bank.add_account(account_3)?;
+
+    // Joint account - John Wick can sign for John McClane's
+    // account and Frank Castle can only look at it
+    bank.add_holder(2, 1, HolderRole::AuthorizedSigner)?;
+    bank.add_holder(2, 3, HolderRole::Viewer)?;
+
+    for account in bank.accounts_for_customer(1) {
+        println!(
+            "John Wick holds account {} as {:?}",
+            account.id,
+            account.role_of(1)
+        );
+    }
+
+    bank.remove_holder(2, 3)?;
+
+    if let Some(customer) = bank.customer(1) {
+        println!(
+            "{} <{}> {:?} is {:?}\n",
+            customer.name, customer.contact.email, customer.contact.phone, customer.kyc_status
+        );
+    }
+
+    // Back-dated activity so the statement has something
+    // to show
+    let owner = Actor::Customer(2);
+    let deposit = Operation::Deposit {
+        account_id: 2,
+        amount: 500,
+    };
+    bank.execute(
+        owner,
+        deposit,
+        Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap(),
+    )?;
+    let withdraw = Operation::Withdraw {
+        account_id: 2,
+        amount: 120,
+    };
+    bank.execute(
+        owner,
+        withdraw,
+        Utc.with_ymd_and_hms(2024, 3, 14, 16, 5, 0).unwrap(),
+    )?;
+
+    // The first attempt rejects the whole batch; the
+    // second applies whatever it can. A teller runs both
+    let teller = Actor::Teller(7);
+    let report = bank.import_file(teller, "transactions.csv", ImportMode::AllOrNothing)?;
+    println!(
+        "All or nothing: committed {}, applied {}, rejected {}",
+        report.committed,
+        report.applied,
+        report.rejected.len()
+    );
+
+    let report = bank.import_file(teller, "transactions.csv", ImportMode::SkipBadRows)?;
+    println!(
+        "Skip bad rows: committed {}, applied {}, rejected {}",
+        report.committed,
+        report.applied,
+        report.rejected.len()
+    );
+    for rejected in &report.rejected {
+        println!(
+            "  line {}: {} ({})",
+            rejected.line, rejected.reason, rejected.row
+        );
+    }
+    report.write_rejections("rejections.csv")?;
+    println!("Wrote rejections.csv\n");
+
+    let from = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+
+    if let Some(account) = bank.account(2) {
+        let statement = account.statement(from, to);
+        println!("Statement for account {}:", statement.account_id);
+        println!("{}\n", statement.to_csv());
+    }
+
+    let report = bank.reconcile(from, Utc::now());
+    println!("{}\n", report.to_csv());
+    for line in report.mismatches() {
+        println!(
+            "Account {} is off by {}\n",
+            line.account_id,
+            line.difference()
+        );
+    }
+
+    fraud_demo(&mut bank)?;
+
+    currency_demo(&mut bank)?;
+
+    event_demo(&bank)?;
+
+    loan_demo(&mut bank)?;
+
+    auth_demo(&mut bank)?;
+
+    hold_demo(&mut bank)?;
+
+    ledger_demo(&mut bank)?;
+
+    Ok(bank)
+}
+
+fn main() -> Result<(), Error> {
+    demos();
+
+    lifecycle_demo()?;
+
+    let bank = bank_demo()?;
 
     println!("Total balance in bank: {}\n", bank.total_balance());
     println!("Account summaries: {:#?}\n", bank.summary());
 
     println!("{:#?}", bank);
+
+    Ok(())
 }
 
 /*
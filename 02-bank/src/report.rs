@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+
+use super::transaction::{Transaction, TransactionKind};
+
+// Dates in every export look like '2024-03-01T09:30:00Z'
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/*
+    ### Date ranges ###
+        - Every range is half-open: 'from' is included,
+        'to' is not
+        - Consecutive ranges (Jan 1 - Feb 1, Feb 1 - Mar 1)
+        never count a transaction twice
+*/
+
+#[derive(Debug)]
+pub struct Statement<'a> {
+    pub account_id: u32,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub opening_balance: i64,
+    pub transactions: Vec<&'a Transaction>,
+    pub closing_balance: i64,
+}
+
+// Inherent implementation
+impl Statement<'_> {
+    pub fn to_csv(&self) -> String {
+        let mut lines = vec![String::from("date,description,debit,credit,balance")];
+
+        lines.push(format!(
+            "{},Opening balance,,,{}",
+            self.from.format(DATE_FORMAT),
+            self.opening_balance
+        ));
+
+        let mut balance = self.opening_balance;
+
+        for transaction in &self.transactions {
+            balance += transaction.signed_amount() as i64;
+
+            let (description, debit, credit) = match transaction.kind {
+                TransactionKind::Deposit => {
+                    ("Deposit", String::new(), transaction.amount.to_string())
+                }
+                TransactionKind::Withdrawal => {
+                    ("Withdrawal", transaction.amount.to_string(), String::new())
+                }
+            };
+
+            lines.push(format!(
+                "{},{},{},{},{}",
+                transaction.at.format(DATE_FORMAT),
+                description,
+                debit,
+                credit,
+                balance
+            ));
+        }
+
+        lines.push(format!(
+            "{},Closing balance,,,{}",
+            self.to.format(DATE_FORMAT),
+            self.closing_balance
+        ));
+
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug)]
+pub struct ReconciliationLine {
+    pub account_id: u32,
+    // Activity within the range, derived from the ledger
+    pub opening_balance: i64,
+    pub credits: i64,
+    pub debits: i64,
+    pub closing_balance: i64,
+    // The ledger replayed from the very first transaction
+    // against the balance the account has stored
+    pub ledger_balance: i64,
+    pub stored_balance: i64,
+}
+
+// Inherent implementation
+impl ReconciliationLine {
+    pub fn difference(&self) -> i64 {
+        self.stored_balance - self.ledger_balance
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        self.difference() != 0
+    }
+}
+
+#[derive(Debug)]
+pub struct ReconciliationReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub lines: Vec<ReconciliationLine>,
+}
+
+// Inherent implementation
+impl ReconciliationReport {
+    pub fn mismatches(&self) -> Vec<&ReconciliationLine> {
+        self.lines
+            .iter()
+            .filter(|line| line.is_mismatch())
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut lines = vec![String::from(
+            "account_id,from,to,opening_balance,credits,debits,closing_balance,ledger_balance,stored_balance,difference,status",
+        )];
+
+        for line in &self.lines {
+            lines.push(format!(
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                line.account_id,
+                self.from.format(DATE_FORMAT),
+                self.to.format(DATE_FORMAT),
+                line.opening_balance,
+                line.credits,
+                line.debits,
+                line.closing_balance,
+                line.ledger_balance,
+                line.stored_balance,
+                line.difference(),
+                if line.is_mismatch() { "MISMATCH" } else { "OK" }
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, Utc};
+
+use super::account::Account;
+use super::transaction::TransactionKind;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    // Goes through, but somebody should take a look
+    Flag(String),
+    Deny(String),
+}
+
+// A transaction that hasn't touched the account yet
+#[derive(Debug, Clone)]
+pub struct ProposedTransaction {
+    pub account_id: u32,
+    pub kind: TransactionKind,
+    pub amount: i32,
+    pub at: DateTime<Utc>,
+}
+
+// 'Debug' is a supertrait so a 'Bank' holding rules can
+// still be printed with '{:#?}'
+pub trait Rule: Debug {
+    fn name(&self) -> String;
+    fn evaluate(&self, account: &Account, transaction: &ProposedTransaction) -> Decision;
+}
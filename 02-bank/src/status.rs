@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Active,
+    // Deposits are accepted, withdrawals are rejected
+    Frozen,
+    // Terminal state; only reachable with a zero balance
+    Closed,
+    // No recent activity; behaves like 'Frozen' until
+    // the account is activated again
+    Dormant,
+}
+
+// Inherent implementation
+impl AccountStatus {
+    pub fn accepts_deposits(&self) -> bool {
+        !matches!(self, AccountStatus::Closed)
+    }
+
+    pub fn accepts_withdrawals(&self) -> bool {
+        matches!(self, AccountStatus::Active)
+    }
+
+    pub fn can_transition_to(&self, next: AccountStatus) -> bool {
+        match self {
+            AccountStatus::Active => matches!(
+                next,
+                AccountStatus::Frozen | AccountStatus::Dormant | AccountStatus::Closed
+            ),
+            AccountStatus::Frozen => {
+                matches!(next, AccountStatus::Active | AccountStatus::Closed)
+            }
+            AccountStatus::Dormant => matches!(
+                next,
+                AccountStatus::Active | AccountStatus::Frozen | AccountStatus::Closed
+            ),
+            // Nothing comes back from the dead
+            AccountStatus::Closed => false,
+        }
+    }
+}
+
+// Every transition is recorded along with why it happened
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    pub at: DateTime<Utc>,
+    pub from: AccountStatus,
+    pub to: AccountStatus,
+    pub reason: String,
+}
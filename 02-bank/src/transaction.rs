@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    Deposit,
+    Withdrawal,
+}
+
+// One line in an account's ledger; the ledger is the
+// source of truth the stored balance gets checked against
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub at: DateTime<Utc>,
+    pub kind: TransactionKind,
+    pub amount: i32,
+}
+
+// Inherent implementation
+impl Transaction {
+    // Money in is positive, money out is negative
+    pub fn signed_amount(&self) -> i32 {
+        match self.kind {
+            TransactionKind::Deposit => self.amount,
+            TransactionKind::Withdrawal => -self.amount,
+        }
+    }
+}
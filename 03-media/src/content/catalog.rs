@@ -1,4 +1,14 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Error;
+
+use chrono::{DateTime, Utc};
+
+use super::duplicates::DuplicateIndex;
+use super::index::SearchIndex;
 use super::media::Media; // 'super' is the reference to the parent module
+use super::view::{Cursor, SortBy, Sorted, SortedViews};
 
 // TODO: `'a` is a lifetime parameter. What is that?
 pub enum MightHaveAValue<'a> {
@@ -6,39 +16,171 @@ pub enum MightHaveAValue<'a> {
     NoValueAvailable,
 }
 
-#[derive(Debug)]
+// Handed out by 'Catalog::add' and never reused, so it
+// stays valid when other items are removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MediaId(pub u64);
+
+impl fmt::Display for MediaId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// 'items' is private so the search and duplicate indexes
+// can't drift out of sync with it; read through 'get' /
+// 'iter'. Ids only ever grow, so the map iterates in the
+// order items were added
 pub struct Catalog {
-    pub items: Vec<Media>,
+    items: BTreeMap<MediaId, Media>,
+    added: HashMap<MediaId, DateTime<Utc>>,
+    index: SearchIndex,
+    duplicates: DuplicateIndex,
+    views: SortedViews,
+    next_id: u64,
+}
+
+// The indexes are derived data - leave them out of '{:#?}'
+impl fmt::Debug for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Catalog")
+            .field("items", &self.items)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Catalog {
     pub fn new() -> Self {
-        Catalog { items: Vec::new() }
+        Catalog {
+            items: BTreeMap::new(),
+            added: HashMap::new(),
+            index: SearchIndex::new(),
+            duplicates: DuplicateIndex::default(),
+            views: SortedViews::default(),
+            next_id: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
     }
 
-    pub fn add(&mut self, media: Media) {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MediaId, &Media)> {
+        self.items.iter().map(|(id, media)| (*id, media))
+    }
+
+    pub fn add(&mut self, media: Media) -> Result<MediaId, Error> {
         // We are taking ownership of 'media' here
-        self.items.push(media);
+        self.add_with_id(MediaId(self.next_id), media)
     }
 
-    pub fn get_by_index(&self, index: usize) -> MightHaveAValue {
-        if self.items.len() > index {
+    // For imports that carry their own ids; later 'add's
+    // continue after the highest id seen
+    pub fn add_with_id(&mut self, id: MediaId, media: Media) -> Result<MediaId, Error> {
+        if self.items.contains_key(&id) {
+            return Err(Error::other(format!("id {} is already in use", id)));
+        }
+
+        media.validate()?;
+
+        if let Some(existing) = self.duplicates.find(&media, None) {
+            return Err(Error::other(format!(
+                "'{}' duplicates {}",
+                media.description(),
+                existing
+            )));
+        }
+
+        self.next_id = self.next_id.max(id.0 + 1);
+
+        let added = Utc::now();
+        self.index.insert(id, &media.description());
+        self.duplicates.insert(id, &media);
+        self.views.insert(id, &media, added);
+        self.added.insert(id, added);
+        self.items.insert(id, media);
+
+        Ok(id)
+    }
+
+    pub fn added_at(&self, id: MediaId) -> Option<DateTime<Utc>> {
+        self.added.get(&id).copied()
+    }
+
+    // Replaces the item in place; the id stays the same
+    pub fn update(&mut self, id: MediaId, media: Media) -> Result<Media, Error> {
+        if !self.items.contains_key(&id) {
+            return Err(Error::other(format!("no media with id {}", id)));
+        }
+
+        media.validate()?;
+
+        if let Some(existing) = self.duplicates.find(&media, Some(id)) {
+            return Err(Error::other(format!(
+                "'{}' duplicates {}",
+                media.description(),
+                existing
+            )));
+        }
+
+        let added = self.added[&id];
+        let previous = self.items.insert(id, media).expect("checked above");
+        self.index.remove(id, &previous.description());
+        self.index.insert(id, &self.items[&id].description());
+        self.duplicates.remove(id, &previous);
+        self.duplicates.insert(id, &self.items[&id]);
+        self.views.remove(id, &previous, added);
+        self.views.insert(id, &self.items[&id], added);
+
+        Ok(previous)
+    }
+
+    pub fn remove(&mut self, id: MediaId) -> Option<Media> {
+        let media = self.items.remove(&id)?;
+        let added = self.added.remove(&id).expect("added with the item");
+        self.index.remove(id, &media.description());
+        self.duplicates.remove(id, &media);
+        self.views.remove(id, &media, added);
+
+        Some(media)
+    }
+
+    // Full-text search over titles, creators and the rest
+    // of the description, best match first
+    pub fn search_text(&self, text: &str) -> Vec<(MediaId, &Media, f64)> {
+        self.index
+            .search(text)
+            .into_iter()
+            .map(|(id, score)| (id, &self.items[&id], score))
+            .collect()
+    }
+
+    pub fn sorted(&self, sort: SortBy) -> Sorted<'_> {
+        self.views.sorted(sort, None, &self.items)
+    }
+
+    // Everything after the cursor, in the cursor's order
+    pub fn sorted_after(&self, cursor: &Cursor) -> Sorted<'_> {
+        self.views.sorted(cursor.sort, Some(cursor), &self.items)
+    }
+
+    pub fn lookup(&self, id: MediaId) -> MightHaveAValue<'_> {
+        match self.items.get(&id) {
             // Something to return
 
             // Also, we don't want to transfer ownership,
             // hance the use of '&'
-            MightHaveAValue::ThereIsAValue(&self.items[index])
-        } else {
+            Some(media) => MightHaveAValue::ThereIsAValue(media),
             // Nothing to return
-            MightHaveAValue::NoValueAvailable
+            None => MightHaveAValue::NoValueAvailable,
         }
     }
 
-    pub fn get_by_index_new(&self, index: usize) -> Option<&Media> {
-        if self.items.len() > index {
-            Some(&self.items[index])
-        } else {
-            None
-        }
+    pub fn get(&self, id: MediaId) -> Option<&Media> {
+        self.items.get(&id)
     }
 }
@@ -0,0 +1,709 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Error;
+
+use chrono::{Days, NaiveDate};
+
+use super::catalog::{Catalog, MediaId};
+use super::employee::Employee;
+
+/*
+    ### Circulation ###
+    - An item is in exactly one state:
+        Available --checkout--> OnLoan
+        OnLoan --renew--> OnLoan (later due date)
+        OnLoan --return--> Available, or OnHoldShelf when
+        someone is waiting
+        OnHoldShelf --checkout by that patron--> OnLoan
+        OnHoldShelf --pickup window passes--> next patron's
+        OnHoldShelf, or Available
+    - Holds can only be placed on items that aren't
+    Available; an Available item is simply checked out
+    - Fines accrue per day late, are charged on return and
+    block new checkouts and renewals until paid down
+*/
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub loan_days: u64,
+    pub max_renewals: u32,
+    pub max_loans: usize,
+    // Days a returned item waits for the next patron in the
+    // holds queue
+    pub pickup_days: u64,
+    pub fine_per_day_cents: u32,
+    // A single fine never exceeds this
+    pub max_fine_cents: u32,
+    // Outstanding fines above this block borrowing
+    pub fine_limit_cents: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            loan_days: 21,
+            max_renewals: 2,
+            max_loans: 5,
+            pickup_days: 7,
+            fine_per_day_cents: 25,
+            max_fine_cents: 1000,
+            fine_limit_cents: 500,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Patron {
+    pub id: u32,
+    pub employee: Employee,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loan {
+    pub patron_id: u32,
+    pub checked_out: NaiveDate,
+    pub due: NaiveDate,
+    pub renewals: u32,
+}
+
+// Inherent implementation
+impl Loan {
+    pub fn days_overdue(&self, today: NaiveDate) -> u64 {
+        (today - self.due).num_days().max(0) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemState {
+    Available,
+    OnLoan(Loan),
+    OnHoldShelf { patron_id: u32, until: NaiveDate },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fine {
+    pub patron_id: u32,
+    pub media_id: MediaId,
+    pub days_overdue: u64,
+    pub cents: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Circulation {
+    pub policy: Policy,
+    patrons: HashMap<u32, Patron>,
+    // Items missing from here are Available
+    states: BTreeMap<MediaId, ItemState>,
+    holds: HashMap<MediaId, VecDeque<u32>>,
+    fines: Vec<Fine>,
+    // Payments not yet matched against fines, per patron
+    paid_cents: HashMap<u32, u32>,
+}
+
+// Inherent implementation
+impl Circulation {
+    pub fn new(policy: Policy) -> Self {
+        Circulation {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_patron(&mut self, id: u32, employee: Employee) -> Result<(), Error> {
+        if self.patrons.contains_key(&id) {
+            return Err(Error::other(format!("patron {} already exists", id)));
+        }
+
+        self.patrons.insert(id, Patron { id, employee });
+        Ok(())
+    }
+
+    pub fn patron(&self, id: u32) -> Option<&Patron> {
+        self.patrons.get(&id)
+    }
+
+    pub fn state(&self, media_id: MediaId) -> &ItemState {
+        self.states.get(&media_id).unwrap_or(&ItemState::Available)
+    }
+
+    pub fn loans_for(&self, patron_id: u32) -> Vec<(MediaId, &Loan)> {
+        self.states
+            .iter()
+            .filter_map(|(media_id, state)| match state {
+                ItemState::OnLoan(loan) if loan.patron_id == patron_id => Some((*media_id, loan)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn holds_queue(&self, media_id: MediaId) -> Vec<u32> {
+        self.holds
+            .get(&media_id)
+            .map(|queue| queue.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn checkout(
+        &mut self,
+        catalog: &Catalog,
+        media_id: MediaId,
+        patron_id: u32,
+        today: NaiveDate,
+    ) -> Result<NaiveDate, Error> {
+        if catalog.get(media_id).is_none() {
+            return Err(Error::other(format!("no media with id {}", media_id)));
+        }
+        self.check_can_borrow(patron_id)?;
+
+        if self.loans_for(patron_id).len() >= self.policy.max_loans {
+            return Err(Error::other(format!(
+                "patron {} already has {} loans",
+                patron_id, self.policy.max_loans
+            )));
+        }
+
+        match self.state(media_id) {
+            ItemState::Available => {}
+            ItemState::OnHoldShelf {
+                patron_id: holder, ..
+            } if *holder == patron_id => {}
+            ItemState::OnHoldShelf { .. } => {
+                return Err(Error::other(format!(
+                    "{} is on the hold shelf for another patron",
+                    media_id
+                )));
+            }
+            ItemState::OnLoan(_) => {
+                return Err(Error::other(format!("{} is already on loan", media_id)));
+            }
+        }
+
+        let due = today + Days::new(self.policy.loan_days);
+        self.states.insert(
+            media_id,
+            ItemState::OnLoan(Loan {
+                patron_id,
+                checked_out: today,
+                due,
+                renewals: 0,
+            }),
+        );
+
+        Ok(due)
+    }
+
+    // Charges a fine if late, then passes the item to the
+    // first patron waiting for it
+    pub fn return_item(
+        &mut self,
+        media_id: MediaId,
+        today: NaiveDate,
+    ) -> Result<Option<Fine>, Error> {
+        let ItemState::OnLoan(loan) = self.state(media_id).clone() else {
+            return Err(Error::other(format!("{} is not on loan", media_id)));
+        };
+
+        let days_overdue = loan.days_overdue(today);
+        let fine = (days_overdue > 0).then(|| Fine {
+            patron_id: loan.patron_id,
+            media_id,
+            days_overdue,
+            cents: (days_overdue as u32)
+                .saturating_mul(self.policy.fine_per_day_cents)
+                .min(self.policy.max_fine_cents),
+        });
+
+        if let Some(fine) = &fine {
+            self.fines.push(fine.clone());
+        }
+
+        self.pass_to_next_hold(media_id, today);
+
+        Ok(fine)
+    }
+
+    pub fn renew(
+        &mut self,
+        media_id: MediaId,
+        patron_id: u32,
+        today: NaiveDate,
+    ) -> Result<NaiveDate, Error> {
+        self.check_can_borrow(patron_id)?;
+
+        let has_holds = !self.holds_queue(media_id).is_empty();
+        let policy = self.policy.clone();
+
+        let Some(ItemState::OnLoan(loan)) = self.states.get_mut(&media_id) else {
+            return Err(Error::other(format!("{} is not on loan", media_id)));
+        };
+
+        if loan.patron_id != patron_id {
+            return Err(Error::other(format!(
+                "{} is not on loan to patron {}",
+                media_id, patron_id
+            )));
+        }
+        if loan.days_overdue(today) > 0 {
+            return Err(Error::other(format!("{} is overdue", media_id)));
+        }
+        if loan.renewals >= policy.max_renewals {
+            return Err(Error::other(format!(
+                "{} has already been renewed {} times",
+                media_id, policy.max_renewals
+            )));
+        }
+        if has_holds {
+            return Err(Error::other(format!("{} has holds waiting", media_id)));
+        }
+
+        loan.renewals += 1;
+        loan.due = today + Days::new(policy.loan_days);
+
+        Ok(loan.due)
+    }
+
+    // Returns the patron's 1-based place in the queue
+    pub fn place_hold(
+        &mut self,
+        catalog: &Catalog,
+        media_id: MediaId,
+        patron_id: u32,
+    ) -> Result<usize, Error> {
+        // Anything not on loan looks available, so an unknown
+        // id has to be caught before the state is checked
+        if catalog.get(media_id).is_none() {
+            return Err(Error::other(format!("no media with id {}", media_id)));
+        }
+        if !self.patrons.contains_key(&patron_id) {
+            return Err(Error::other(format!("no patron with id {}", patron_id)));
+        }
+
+        match self.state(media_id) {
+            ItemState::Available => {
+                return Err(Error::other(format!(
+                    "{} is available - check it out instead",
+                    media_id
+                )));
+            }
+            ItemState::OnLoan(loan) if loan.patron_id == patron_id => {
+                return Err(Error::other(format!(
+                    "patron {} already has {}",
+                    patron_id, media_id
+                )));
+            }
+            ItemState::OnHoldShelf {
+                patron_id: holder, ..
+            } if *holder == patron_id => {
+                return Err(Error::other(format!(
+                    "{} is already waiting for patron {}",
+                    media_id, patron_id
+                )));
+            }
+            _ => {}
+        }
+
+        let queue = self.holds.entry(media_id).or_default();
+        if queue.contains(&patron_id) {
+            return Err(Error::other(format!(
+                "patron {} already has a hold on {}",
+                patron_id, media_id
+            )));
+        }
+
+        queue.push_back(patron_id);
+        Ok(queue.len())
+    }
+
+    pub fn cancel_hold(&mut self, media_id: MediaId, patron_id: u32) -> Result<(), Error> {
+        let no_hold = || Error::other(format!("patron {} has no hold on {}", patron_id, media_id));
+
+        let queue = self.holds.get_mut(&media_id).ok_or_else(no_hold)?;
+        let position = queue
+            .iter()
+            .position(|id| *id == patron_id)
+            .ok_or_else(no_hold)?;
+
+        queue.remove(position);
+        Ok(())
+    }
+
+    // Uncollected items move on to the next patron in line;
+    // returns the items whose pickup window closed
+    pub fn expire_hold_shelf(&mut self, today: NaiveDate) -> Vec<MediaId> {
+        let expired: Vec<MediaId> = self
+            .states
+            .iter()
+            .filter_map(|(media_id, state)| match state {
+                ItemState::OnHoldShelf { until, .. } if *until < today => Some(*media_id),
+                _ => None,
+            })
+            .collect();
+
+        for media_id in &expired {
+            self.pass_to_next_hold(*media_id, today);
+        }
+
+        expired
+    }
+
+    // Loans past their due date, most overdue first
+    pub fn overdue(&self, today: NaiveDate) -> Vec<(MediaId, &Loan, u64)> {
+        let mut overdue: Vec<(MediaId, &Loan, u64)> = self
+            .states
+            .iter()
+            .filter_map(|(media_id, state)| match state {
+                ItemState::OnLoan(loan) if loan.days_overdue(today) > 0 => {
+                    Some((*media_id, loan, loan.days_overdue(today)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        overdue.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        overdue
+    }
+
+    pub fn fines_for(&self, patron_id: u32) -> Vec<&Fine> {
+        self.fines
+            .iter()
+            .filter(|fine| fine.patron_id == patron_id)
+            .collect()
+    }
+
+    pub fn outstanding_cents(&self, patron_id: u32) -> u32 {
+        let charged: u32 = self
+            .fines_for(patron_id)
+            .iter()
+            .map(|fine| fine.cents)
+            .sum();
+
+        charged.saturating_sub(self.paid_cents.get(&patron_id).copied().unwrap_or(0))
+    }
+
+    // Returns what is still owed
+    pub fn pay_fines(&mut self, patron_id: u32, cents: u32) -> Result<u32, Error> {
+        let outstanding = self.outstanding_cents(patron_id);
+        if cents > outstanding {
+            return Err(Error::other(format!(
+                "payment of {} exceeds the {} owed",
+                cents, outstanding
+            )));
+        }
+
+        *self.paid_cents.entry(patron_id).or_insert(0) += cents;
+        Ok(outstanding - cents)
+    }
+
+    fn check_can_borrow(&self, patron_id: u32) -> Result<(), Error> {
+        if !self.patrons.contains_key(&patron_id) {
+            return Err(Error::other(format!("no patron with id {}", patron_id)));
+        }
+
+        let outstanding = self.outstanding_cents(patron_id);
+        if outstanding > self.policy.fine_limit_cents {
+            return Err(Error::other(format!(
+                "patron {} owes {} in fines",
+                patron_id, outstanding
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn pass_to_next_hold(&mut self, media_id: MediaId, today: NaiveDate) {
+        let next = self
+            .holds
+            .get_mut(&media_id)
+            .and_then(|queue| queue.pop_front());
+
+        match next {
+            Some(patron_id) => {
+                self.states.insert(
+                    media_id,
+                    ItemState::OnHoldShelf {
+                        patron_id,
+                        until: today + Days::new(self.policy.pickup_days),
+                    },
+                );
+            }
+            None => {
+                self.states.remove(&media_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Media;
+    use crate::content::metadata::Metadata;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn book(title: &str) -> Media {
+        Media::Book {
+            author: String::from("An author"),
+            title: String::from(title),
+            metadata: Metadata::default(),
+        }
+    }
+
+    fn employee(id: u32, name: &str) -> Employee {
+        Employee {
+            id,
+            name: String::from(name),
+        }
+    }
+
+    // Two books and three patrons; loans last 7 days
+    fn setup() -> (Catalog, Circulation, MediaId, MediaId) {
+        let mut catalog = Catalog::new();
+        let first = catalog.add(book("First")).unwrap();
+        let second = catalog.add(book("Second")).unwrap();
+
+        let mut circulation = Circulation::new(Policy {
+            loan_days: 7,
+            max_loans: 2,
+            ..Policy::default()
+        });
+        for (id, name) in [(1, "Ann"), (2, "Bob"), (3, "Cy")] {
+            circulation.add_patron(id, employee(id, name)).unwrap();
+        }
+
+        (catalog, circulation, first, second)
+    }
+
+    #[test]
+    fn checkout_moves_available_to_on_loan() {
+        let (catalog, mut circulation, first, _) = setup();
+
+        assert_eq!(circulation.state(first), &ItemState::Available);
+        let due = circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+
+        assert_eq!(due, day(8));
+        assert_eq!(
+            circulation.state(first),
+            &ItemState::OnLoan(Loan {
+                patron_id: 1,
+                checked_out: day(1),
+                due: day(8),
+                renewals: 0,
+            })
+        );
+        assert_eq!(circulation.loans_for(1).len(), 1);
+    }
+
+    #[test]
+    fn checkout_rejects_unknown_items_patrons_and_loaned_items() {
+        let (catalog, mut circulation, first, _) = setup();
+
+        assert!(circulation
+            .checkout(&catalog, MediaId(99), 1, day(1))
+            .is_err());
+        assert!(circulation.checkout(&catalog, first, 99, day(1)).is_err());
+
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        assert!(circulation.checkout(&catalog, first, 2, day(1)).is_err());
+    }
+
+    #[test]
+    fn checkout_respects_loan_limit() {
+        let (mut catalog, mut circulation, first, second) = setup();
+        let third = catalog.add(book("Third")).unwrap();
+
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.checkout(&catalog, second, 1, day(1)).unwrap();
+
+        assert!(circulation.checkout(&catalog, third, 1, day(1)).is_err());
+        assert_eq!(circulation.state(third), &ItemState::Available);
+    }
+
+    #[test]
+    fn return_on_time_makes_item_available_without_fine() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+
+        assert_eq!(circulation.return_item(first, day(8)).unwrap(), None);
+        assert_eq!(circulation.state(first), &ItemState::Available);
+        assert!(circulation.loans_for(1).is_empty());
+    }
+
+    #[test]
+    fn return_of_item_not_on_loan_fails() {
+        let (_, mut circulation, first, _) = setup();
+
+        assert!(circulation.return_item(first, day(1)).is_err());
+    }
+
+    #[test]
+    fn late_return_charges_capped_fine() {
+        let (catalog, mut circulation, first, second) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.checkout(&catalog, second, 2, day(1)).unwrap();
+
+        let fine = circulation.return_item(first, day(11)).unwrap().unwrap();
+        assert_eq!(fine.days_overdue, 3);
+        assert_eq!(fine.cents, 75);
+
+        // Months late - capped at 'max_fine_cents'
+        let late = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let fine = circulation.return_item(second, late).unwrap().unwrap();
+        assert_eq!(fine.cents, 1000);
+        assert_eq!(circulation.outstanding_cents(2), 1000);
+    }
+
+    #[test]
+    fn overdue_lists_late_loans_most_overdue_first() {
+        let (catalog, mut circulation, first, second) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.checkout(&catalog, second, 2, day(3)).unwrap();
+
+        assert!(circulation.overdue(day(8)).is_empty());
+
+        let overdue = circulation.overdue(day(12));
+        let ids: Vec<(MediaId, u64)> = overdue.iter().map(|(id, _, days)| (*id, *days)).collect();
+        assert_eq!(ids, vec![(first, 4), (second, 2)]);
+    }
+
+    #[test]
+    fn fines_block_borrowing_until_paid() {
+        let (catalog, mut circulation, first, second) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        let late = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        circulation.return_item(first, late).unwrap();
+
+        assert!(circulation.checkout(&catalog, second, 1, late).is_err());
+        assert!(circulation.pay_fines(1, 5000).is_err());
+
+        assert_eq!(circulation.pay_fines(1, 600).unwrap(), 400);
+        circulation.checkout(&catalog, second, 1, late).unwrap();
+    }
+
+    #[test]
+    fn renew_extends_due_date_up_to_limit() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+
+        assert_eq!(circulation.renew(first, 1, day(5)).unwrap(), day(12));
+        assert_eq!(circulation.renew(first, 1, day(10)).unwrap(), day(17));
+        assert!(circulation.renew(first, 1, day(15)).is_err());
+
+        let ItemState::OnLoan(loan) = circulation.state(first) else {
+            panic!("expected a loan");
+        };
+        assert_eq!((loan.renewals, loan.due), (2, day(17)));
+    }
+
+    #[test]
+    fn renew_is_refused_for_other_patrons_overdue_loans_and_holds() {
+        let (catalog, mut circulation, first, second) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.checkout(&catalog, second, 1, day(1)).unwrap();
+
+        assert!(circulation.renew(first, 2, day(2)).is_err());
+        assert!(circulation.renew(first, 1, day(9)).is_err());
+
+        circulation.place_hold(&catalog, second, 2).unwrap();
+        assert!(circulation.renew(second, 1, day(2)).is_err());
+    }
+
+    #[test]
+    fn holds_are_only_placed_on_unavailable_items() {
+        let (catalog, mut circulation, first, _) = setup();
+
+        assert!(circulation.place_hold(&catalog, first, 2).is_err());
+
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        assert!(circulation.place_hold(&catalog, first, 1).is_err());
+        assert!(circulation.place_hold(&catalog, first, 99).is_err());
+
+        assert_eq!(circulation.place_hold(&catalog, first, 2).unwrap(), 1);
+        assert_eq!(circulation.place_hold(&catalog, first, 3).unwrap(), 2);
+        assert!(circulation.place_hold(&catalog, first, 2).is_err());
+        assert_eq!(circulation.holds_queue(first), vec![2, 3]);
+    }
+
+    #[test]
+    fn holds_on_unknown_items_are_refused() {
+        let (catalog, mut circulation, _, _) = setup();
+
+        let error = circulation
+            .place_hold(&catalog, MediaId(99), 2)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "no media with id #99");
+        assert!(circulation.holds_queue(MediaId(99)).is_empty());
+    }
+
+    #[test]
+    fn return_with_holds_moves_item_to_hold_shelf() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.place_hold(&catalog, first, 2).unwrap();
+        circulation.place_hold(&catalog, first, 3).unwrap();
+
+        circulation.return_item(first, day(5)).unwrap();
+
+        assert_eq!(
+            circulation.state(first),
+            &ItemState::OnHoldShelf {
+                patron_id: 2,
+                until: day(12),
+            }
+        );
+        assert_eq!(circulation.holds_queue(first), vec![3]);
+    }
+
+    #[test]
+    fn hold_shelf_item_goes_only_to_its_patron() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.place_hold(&catalog, first, 2).unwrap();
+        circulation.return_item(first, day(5)).unwrap();
+
+        assert!(circulation.checkout(&catalog, first, 3, day(6)).is_err());
+        assert!(circulation.place_hold(&catalog, first, 2).is_err());
+
+        circulation.checkout(&catalog, first, 2, day(6)).unwrap();
+        assert!(matches!(
+            circulation.state(first),
+            ItemState::OnLoan(Loan { patron_id: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn uncollected_holds_pass_to_next_patron_then_become_available() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.place_hold(&catalog, first, 2).unwrap();
+        circulation.place_hold(&catalog, first, 3).unwrap();
+        circulation.return_item(first, day(5)).unwrap();
+
+        assert!(circulation.expire_hold_shelf(day(12)).is_empty());
+
+        assert_eq!(circulation.expire_hold_shelf(day(13)), vec![first]);
+        assert_eq!(
+            circulation.state(first),
+            &ItemState::OnHoldShelf {
+                patron_id: 3,
+                until: day(20),
+            }
+        );
+
+        assert_eq!(circulation.expire_hold_shelf(day(21)), vec![first]);
+        assert_eq!(circulation.state(first), &ItemState::Available);
+    }
+
+    #[test]
+    fn cancelled_hold_leaves_the_queue() {
+        let (catalog, mut circulation, first, _) = setup();
+        circulation.checkout(&catalog, first, 1, day(1)).unwrap();
+        circulation.place_hold(&catalog, first, 2).unwrap();
+
+        circulation.cancel_hold(first, 2).unwrap();
+        assert!(circulation.cancel_hold(first, 2).is_err());
+
+        circulation.return_item(first, day(5)).unwrap();
+        assert_eq!(circulation.state(first), &ItemState::Available);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::io::Error;
+
+use csv::{ReaderBuilder, StringRecord, Writer};
+
+use super::catalog::{Catalog, MediaId};
+use super::import::{format_seconds, join_list, parse_seconds, split_list, ImportReport};
+use super::media::Media;
+use super::metadata::{Identifier, Metadata, Rating};
+
+/*
+    ### CSV format ###
+        id,kind,title,author,director,show,series,season,episode_number,episode_title,year,genres,duration,identifiers,language,ratings,tags
+        3,Movie,Interstellar,,A director,,,,,,2014,Sci-Fi;Drama,10140,IMDB:tt0816692,English,8.7/10 IMDb,staff-pick
+
+        - Each kind has its own column set; columns outside
+        it must be left empty
+            - AudioBook, Book: title, author
+            - Movie: title, director
+            - Podcast: show, season (optional),
+            episode_number, episode_title
+            - TvEpisode: series, season, episode_number,
+            episode_title
+            - Placeholder: nothing, not even metadata
+        - 'kind' is required; 'id' may be empty and any other
+        column may be missing from the header
+        - Lists ('genres', 'identifiers', 'ratings', 'tags')
+        are ';'-separated, with '\' escaping ';' and '\'
+        - 'duration' is in seconds
+*/
+const COLUMNS: [&str; 17] = [
+    "id",
+    "kind",
+    "title",
+    "author",
+    "director",
+    "show",
+    "series",
+    "season",
+    "episode_number",
+    "episode_title",
+    "year",
+    "genres",
+    "duration",
+    "identifiers",
+    "language",
+    "ratings",
+    "tags",
+];
+
+const METADATA_COLUMNS: [&str; 7] = [
+    "year",
+    "genres",
+    "duration",
+    "identifiers",
+    "language",
+    "ratings",
+    "tags",
+];
+
+// Fields of one row, looked up by column name
+struct Row<'a> {
+    columns: &'a HashMap<&'a str, usize>,
+    record: &'a StringRecord,
+}
+
+// Inherent implementation
+impl Row<'_> {
+    fn get(&self, column: &str) -> &str {
+        self.columns
+            .get(column)
+            .and_then(|index| self.record.get(*index))
+            .unwrap_or("")
+    }
+
+    fn to_media(&self) -> Result<Media, Error> {
+        let kind = self.get("kind");
+
+        let own: &[&str] = match kind {
+            "AudioBook" | "Book" => &["title", "author"],
+            "Movie" => &["title", "director"],
+            "Podcast" => &["show", "season", "episode_number", "episode_title"],
+            "TvEpisode" => &["series", "season", "episode_number", "episode_title"],
+            "Placeholder" => &[],
+            "" => return Err(Error::other("missing kind")),
+            _ => return Err(Error::other(format!("unknown kind '{}'", kind))),
+        };
+
+        for column in &COLUMNS[2..] {
+            let allowed = own.contains(column)
+                || (kind != "Placeholder" && METADATA_COLUMNS.contains(column));
+            if !allowed && !self.get(column).is_empty() {
+                return Err(Error::other(format!(
+                    "column '{}' doesn't belong to a {}",
+                    column, kind
+                )));
+            }
+        }
+
+        let field = |column: &str| self.get(column).to_string();
+
+        Ok(match kind {
+            "AudioBook" => Media::AudioBook {
+                author: field("author"),
+                title: field("title"),
+                metadata: self.metadata()?,
+            },
+            "Book" => Media::Book {
+                author: field("author"),
+                title: field("title"),
+                metadata: self.metadata()?,
+            },
+            "Movie" => Media::Movie {
+                director: field("director"),
+                title: field("title"),
+                metadata: self.metadata()?,
+            },
+            "Podcast" => Media::Podcast {
+                show: field("show"),
+                season: match self.get("season") {
+                    "" => None,
+                    _ => Some(self.number("season")?),
+                },
+                episode_number: self.number("episode_number")?,
+                episode_title: field("episode_title"),
+                metadata: self.metadata()?,
+            },
+            "TvEpisode" => Media::TvEpisode {
+                series: field("series"),
+                season: self.number("season")?,
+                episode_number: self.number("episode_number")?,
+                episode_title: field("episode_title"),
+                metadata: self.metadata()?,
+            },
+            _ => Media::Placeholder,
+        })
+    }
+
+    fn number(&self, column: &str) -> Result<u32, Error> {
+        let value = self.get(column);
+        value
+            .parse()
+            .map_err(|_| Error::other(format!("invalid {} '{}'", column, value)))
+    }
+
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let optional = |column: &str| match self.get(column) {
+            "" => None,
+            value => Some(value),
+        };
+
+        Ok(Metadata {
+            year: optional("year")
+                .map(|year| {
+                    year.parse()
+                        .map_err(|_| Error::other(format!("invalid year '{}'", year)))
+                })
+                .transpose()?,
+            genres: split_list(self.get("genres")),
+            duration: optional("duration").map(parse_seconds).transpose()?,
+            identifiers: split_list(self.get("identifiers"))
+                .iter()
+                .map(|text| Identifier::from_text(text))
+                .collect::<Result<_, _>>()?,
+            language: optional("language").map(String::from),
+            ratings: split_list(self.get("ratings"))
+                .iter()
+                .map(|text| Rating::from_text(text))
+                .collect::<Result<_, _>>()?,
+            tags: split_list(self.get("tags")),
+        })
+    }
+}
+
+fn to_record(id: MediaId, media: &Media) -> Vec<String> {
+    let mut fields: HashMap<&str, String> = HashMap::new();
+    fields.insert("id", id.0.to_string());
+
+    match media {
+        Media::AudioBook { author, title, .. } => {
+            fields.insert("kind", String::from("AudioBook"));
+            fields.insert("author", author.clone());
+            fields.insert("title", title.clone());
+        }
+        Media::Book { author, title, .. } => {
+            fields.insert("kind", String::from("Book"));
+            fields.insert("author", author.clone());
+            fields.insert("title", title.clone());
+        }
+        Media::Movie {
+            director, title, ..
+        } => {
+            fields.insert("kind", String::from("Movie"));
+            fields.insert("director", director.clone());
+            fields.insert("title", title.clone());
+        }
+        Media::Podcast {
+            show,
+            season,
+            episode_number,
+            episode_title,
+            ..
+        } => {
+            fields.insert("kind", String::from("Podcast"));
+            fields.insert("show", show.clone());
+            if let Some(season) = season {
+                fields.insert("season", season.to_string());
+            }
+            fields.insert("episode_number", episode_number.to_string());
+            fields.insert("episode_title", episode_title.clone());
+        }
+        Media::TvEpisode {
+            series,
+            season,
+            episode_number,
+            episode_title,
+            ..
+        } => {
+            fields.insert("kind", String::from("TvEpisode"));
+            fields.insert("series", series.clone());
+            fields.insert("season", season.to_string());
+            fields.insert("episode_number", episode_number.to_string());
+            fields.insert("episode_title", episode_title.clone());
+        }
+        Media::Placeholder => {
+            fields.insert("kind", String::from("Placeholder"));
+        }
+    }
+
+    if let Some(metadata) = media.metadata() {
+        if let Some(year) = metadata.year {
+            fields.insert("year", year.to_string());
+        }
+        fields.insert("genres", join_list(&metadata.genres));
+        if let Some(duration) = metadata.duration {
+            fields.insert("duration", format_seconds(duration));
+        }
+        fields.insert(
+            "identifiers",
+            join_list(
+                &metadata
+                    .identifiers
+                    .iter()
+                    .map(Identifier::to_text)
+                    .collect::<Vec<_>>(),
+            ),
+        );
+        if let Some(language) = &metadata.language {
+            fields.insert("language", language.clone());
+        }
+        fields.insert(
+            "ratings",
+            join_list(
+                &metadata
+                    .ratings
+                    .iter()
+                    .map(Rating::to_text)
+                    .collect::<Vec<_>>(),
+            ),
+        );
+        fields.insert("tags", join_list(&metadata.tags));
+    }
+
+    COLUMNS
+        .iter()
+        .map(|column| fields.remove(column).unwrap_or_default())
+        .collect()
+}
+
+// Inherent implementation
+impl Catalog {
+    pub fn to_csv(&self) -> Result<String, Error> {
+        let mut writer = Writer::from_writer(Vec::new());
+
+        writer.write_record(COLUMNS).map_err(Error::other)?;
+        for (id, media) in self.iter() {
+            writer
+                .write_record(to_record(id, media))
+                .map_err(Error::other)?;
+        }
+
+        let bytes = writer.into_inner().map_err(Error::other)?;
+        String::from_utf8(bytes).map_err(Error::other)
+    }
+
+    // Fails only on an unusable header; bad rows are
+    // reported with their line number
+    pub fn import_csv(&mut self, text: &str) -> Result<ImportReport, Error> {
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let header = reader.headers().map_err(Error::other)?.clone();
+        let mut columns = HashMap::new();
+        for (index, name) in header.iter().enumerate() {
+            if !COLUMNS.contains(&name) {
+                return Err(Error::other(format!("unknown column '{}'", name)));
+            }
+            if columns.insert(name, index).is_some() {
+                return Err(Error::other(format!("column '{}' appears twice", name)));
+            }
+        }
+        if !columns.contains_key("kind") {
+            return Err(Error::other("missing 'kind' column"));
+        }
+
+        let mut report = ImportReport::default();
+
+        for result in reader.records() {
+            let record = match result {
+                Ok(record) => record,
+                Err(error) => {
+                    let line = error.position().map_or(0, |position| position.line());
+                    report.reject(line as usize, "", &error.to_string());
+                    continue;
+                }
+            };
+
+            let line = record.position().map_or(0, |position| position.line()) as usize;
+            let row = record.iter().collect::<Vec<_>>().join(",");
+            let fields = Row {
+                columns: &columns,
+                record: &record,
+            };
+
+            let id = match fields.get("id") {
+                "" => None,
+                id => match id.parse() {
+                    Ok(id) => Some(MediaId(id)),
+                    Err(_) => {
+                        report.reject(line, &row, &format!("invalid id '{}'", id));
+                        continue;
+                    }
+                },
+            };
+
+            match fields.to_media() {
+                Ok(media) => self.import_entry(&mut report, line, &row, id, media),
+                Err(error) => report.reject(line, &row, &error.to_string()),
+            }
+        }
+
+        Ok(report)
+    }
+}
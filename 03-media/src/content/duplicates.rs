@@ -0,0 +1,43 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::catalog::MediaId;
+use super::media::{Media, WorkKey};
+
+// Ids by work key, so a duplicate check is a few hash
+// lookups instead of a pass over every item
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    ids: HashMap<WorkKey, BTreeSet<MediaId>>,
+}
+
+// Inherent implementation
+impl DuplicateIndex {
+    pub fn insert(&mut self, id: MediaId, media: &Media) {
+        for key in media.work_keys() {
+            self.ids.entry(key).or_default().insert(id);
+        }
+    }
+
+    pub fn remove(&mut self, id: MediaId, media: &Media) {
+        for key in media.work_keys() {
+            if let Some(ids) = self.ids.get_mut(&key) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.ids.remove(&key);
+                }
+            }
+        }
+    }
+
+    // The lowest id sharing a key with 'media', other than
+    // 'ignore'
+    pub fn find(&self, media: &Media, ignore: Option<MediaId>) -> Option<MediaId> {
+        media
+            .work_keys()
+            .iter()
+            .filter_map(|key| self.ids.get(key))
+            .filter_map(|ids| ids.iter().find(|id| Some(**id) != ignore))
+            .min()
+            .copied()
+    }
+}
@@ -1,4 +1,15 @@
-#[derive(Debug)]
+// Two employees are the same person when their ids match;
+// names can repeat and change
+#[derive(Debug, Clone)]
 pub struct Employee {
+    pub id: u32,
     pub name: String,
 }
+
+impl PartialEq for Employee {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Employee {}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use super::catalog::{Catalog, MediaId};
+use super::media::Media;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Marc,
+}
+
+// Inherent implementation
+impl Format {
+    // '.json', '.csv' or '.mrk' (MARC-lite)
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("json") => Ok(Format::Json),
+            Some("csv") => Ok(Format::Csv),
+            Some("mrk") => Ok(Format::Marc),
+            _ => Err(Error::other(format!(
+                "unknown catalog format for '{}'",
+                path.display()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RejectedRow {
+    // 1-based line of the row (CSV), of the record's first
+    // field (MARC-lite) or where the entry starts (JSON)
+    pub line: usize,
+    pub row: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: Vec<MediaId>,
+    pub rejected: Vec<RejectedRow>,
+}
+
+// Inherent implementation
+impl ImportReport {
+    pub fn reject(&mut self, line: usize, row: &str, reason: &str) {
+        self.rejected.push(RejectedRow {
+            line,
+            row: row.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{} imported, {} rejected",
+            self.imported.len(),
+            self.rejected.len()
+        )];
+
+        for rejected in &self.rejected {
+            lines.push(format!(
+                "  line {}: {} ({})",
+                rejected.line, rejected.reason, rejected.row
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+// Inherent implementation
+impl Catalog {
+    // Entries with an id keep it; entries without one get
+    // a fresh id. Duplicates are rejected like any bad row
+    pub fn import_entry(
+        &mut self,
+        report: &mut ImportReport,
+        line: usize,
+        row: &str,
+        id: Option<MediaId>,
+        media: Media,
+    ) {
+        let added = match id {
+            Some(id) => self.add_with_id(id, media),
+            None => self.add(media),
+        };
+
+        match added {
+            Ok(id) => report.imported.push(id),
+            Err(error) => report.reject(line, row, &error.to_string()),
+        }
+    }
+
+    pub fn import_file(&mut self, path: impl AsRef<Path>) -> Result<ImportReport, Error> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+        let text = fs::read_to_string(path)?;
+
+        match format {
+            Format::Json => self.import_json(&text),
+            Format::Csv => self.import_csv(&text),
+            Format::Marc => Ok(self.import_marc(&text)),
+        }
+    }
+
+    pub fn export_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = match Format::from_path(path)? {
+            Format::Json => self.to_json()?,
+            Format::Csv => self.to_csv()?,
+            Format::Marc => self.to_marc(),
+        };
+
+        fs::write(path, text)
+    }
+}
+
+// Whole seconds print as '4320'; anything finer keeps all
+// nine digits ('4320.000000500') so it reads back exactly
+pub fn format_seconds(duration: Duration) -> String {
+    match duration.subsec_nanos() {
+        0 => duration.as_secs().to_string(),
+        nanos => format!("{}.{:09}", duration.as_secs(), nanos),
+    }
+}
+
+pub fn parse_seconds(text: &str) -> Result<Duration, Error> {
+    let invalid = || Error::other(format!("invalid duration '{}'", text));
+
+    let (seconds, nanos) = match text.split_once('.') {
+        Some((seconds, fraction)) => {
+            if fraction.is_empty()
+                || fraction.len() > 9
+                || !fraction.chars().all(|c| c.is_ascii_digit())
+            {
+                return Err(invalid());
+            }
+            let nanos: u32 = format!("{:0<9}", fraction).parse().map_err(|_| invalid())?;
+            (seconds, nanos)
+        }
+        None => (text, 0),
+    };
+
+    let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+    Ok(Duration::new(seconds, nanos))
+}
+
+// List items are separated by ';'; a literal ';' or '\' in
+// an item is escaped with '\'
+// - Empty items are refused on add ('Metadata::validate'),
+// as [""] and [] would both be written as ''
+pub fn join_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| item.replace('\\', "\\\\").replace(';', "\\;"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+pub fn split_list(text: &str) -> Vec<String> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let mut items = vec![String::new()];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    items.last_mut().unwrap().push(escaped);
+                }
+            }
+            ';' => items.push(String::new()),
+            c => items.last_mut().unwrap().push(c),
+        }
+    }
+
+    items
+}
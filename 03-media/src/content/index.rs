@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use super::catalog::MediaId;
+
+// BM25 tuning; the usual defaults
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+const STOP_WORDS: [&str; 12] = [
+    "a", "an", "and", "at", "by", "for", "in", "of", "on", "the", "to", "with",
+];
+
+/*
+    ### Inverted index ###
+    - Maps every term to the documents containing it and
+    how often (term frequency), so a query only touches
+    the documents that share a term with it
+    - Documents are ranked with BM25: rare terms weigh
+    more than common ones (IDF) and repeated terms give
+    diminishing returns, normalised by document length
+*/
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    postings: HashMap<String, HashMap<MediaId, u32>>,
+    lengths: HashMap<MediaId, usize>,
+    total_length: usize,
+}
+
+// Inherent implementation
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, doc: MediaId, text: &str) {
+        let terms = tokenize(text);
+
+        self.total_length += terms.len();
+        self.lengths.insert(doc, terms.len());
+
+        for term in terms {
+            *self
+                .postings
+                .entry(term)
+                .or_default()
+                .entry(doc)
+                .or_insert(0) += 1;
+        }
+    }
+
+    // 'text' must be what the document was inserted with
+    pub fn remove(&mut self, doc: MediaId, text: &str) {
+        let Some(length) = self.lengths.remove(&doc) else {
+            return;
+        };
+        self.total_length -= length;
+
+        for term in tokenize(text) {
+            if let Some(docs) = self.postings.get_mut(&term) {
+                docs.remove(&doc);
+                if docs.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    // Highest score first; ties keep catalog order (by id)
+    pub fn search(&self, query: &str) -> Vec<(MediaId, f64)> {
+        let count = self.lengths.len() as f64;
+        if count == 0.0 {
+            return Vec::new();
+        }
+        let average_length = self.total_length as f64 / count;
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let mut scores: HashMap<MediaId, f64> = HashMap::new();
+        for term in terms {
+            let Some(docs) = self.postings.get(&term) else {
+                continue;
+            };
+
+            let matching = docs.len() as f64;
+            let idf = ((count - matching + 0.5) / (matching + 0.5) + 1.0).ln();
+
+            for (doc, frequency) in docs {
+                let frequency = *frequency as f64;
+                let length = self.lengths[doc] as f64;
+                let norm = K1 * (1.0 - B + B * length / average_length);
+
+                *scores.entry(*doc).or_insert(0.0) +=
+                    idf * frequency * (K1 + 1.0) / (frequency + norm);
+            }
+        }
+
+        let mut ranked: Vec<(MediaId, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+// Lowercased, stemmed words without stop words
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| stem(&word))
+        .collect()
+}
+
+// A light suffix stripper - enough to fold "stars",
+// "starring" and "starred" together; not a full Porter
+// stemmer
+pub fn stem(word: &str) -> String {
+    const SUFFIXES: [(&str, &str); 8] = [
+        ("ies", "y"),
+        ("ing", ""),
+        ("edly", ""),
+        ("ed", ""),
+        ("ness", ""),
+        ("ment", ""),
+        ("ly", ""),
+        ("s", ""),
+    ];
+
+    for (suffix, replacement) in SUFFIXES {
+        if let Some(root) = word.strip_suffix(suffix) {
+            // Keep short words like "bus" or "class" intact;
+            // "movies" should not become "movy"
+            let minimum = if suffix == "ies" { 4 } else { 3 };
+            if root.chars().count() < minimum || (suffix == "s" && root.ends_with('s')) {
+                continue;
+            }
+
+            let mut root = format!("{}{}", root, replacement);
+
+            // "starr" -> "star", "runn" -> "run"
+            let chars: Vec<char> = root.chars().collect();
+            if replacement.is_empty()
+                && chars.len() > 3
+                && chars[chars.len() - 1] == chars[chars.len() - 2]
+                && !"lsz".contains(chars[chars.len() - 1])
+            {
+                root.pop();
+            }
+
+            return root;
+        }
+    }
+
+    word.to_string()
+}
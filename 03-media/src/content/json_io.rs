@@ -0,0 +1,110 @@
+use std::io::Error;
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::catalog::{Catalog, MediaId};
+use super::import::ImportReport;
+use super::media::Media;
+
+/*
+    ### JSON format ###
+        [
+          {"id": 1, "kind": "Book", "author": "..", "title": "..",
+           "metadata": {"year": 2014, "genres": ["Drama"], ..}},
+          {"kind": "Podcast", "show": "..", "episode_number": 3,
+           "episode_title": ".."}
+        ]
+
+        - 'id' and 'metadata' (and each of its fields) are
+        optional on import
+        - Durations are '{"secs": .., "nanos": ..}'
+*/
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(flatten)]
+    media: Media,
+}
+
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    id: u64,
+    #[serde(flatten)]
+    media: &'a Media,
+}
+
+// Inherent implementation
+impl Catalog {
+    pub fn to_json(&self) -> Result<String, Error> {
+        let entries: Vec<EntryRef> = self
+            .iter()
+            .map(|(id, media)| EntryRef { id: id.0, media })
+            .collect();
+
+        serde_json::to_string_pretty(&entries).map_err(Error::other)
+    }
+
+    // Fails only if the document isn't a JSON array; bad
+    // entries are reported by the line they start on
+    pub fn import_json(&mut self, text: &str) -> Result<ImportReport, Error> {
+        let values: Vec<Value> = serde_json::from_str(text)
+            .map_err(|error| Error::other(format!("not a JSON array of entries: {}", error)))?;
+        let lines = entry_lines(text)?;
+
+        let mut report = ImportReport::default();
+
+        for (value, line) in values.into_iter().zip(lines) {
+            let row = value.to_string();
+
+            match serde_json::from_value::<Entry>(value) {
+                Ok(entry) => {
+                    self.import_entry(&mut report, line, &row, entry.id.map(MediaId), entry.media)
+                }
+                Err(error) => {
+                    report.reject(line, &row, &format!("matches no media variant: {}", error))
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+// The 1-based line each entry of the array starts on; the
+// 'Value's parsed from 'text' don't remember where they came
+// from. 'text' is already known to be a valid array
+fn entry_lines(text: &str) -> Result<Vec<usize>, Error> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    let mut counted = 0;
+    let mut offset = text.find('[').map_or(text.len(), |start| start + 1);
+
+    loop {
+        let rest = &text[offset..];
+        offset += rest.len() - rest.trim_start().len();
+        match text[offset..].chars().next() {
+            Some(']') | None => return Ok(lines),
+            Some(',') => {
+                offset += 1;
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        line += text[counted..offset].matches('\n').count();
+        counted = offset;
+        lines.push(line);
+
+        // Skips over one entry without building it
+        let mut entries = serde_json::Deserializer::from_str(&text[offset..]).into_iter();
+        entries
+            .next()
+            .transpose()
+            .map(|_: Option<IgnoredAny>| ())
+            .map_err(Error::other)?;
+        offset += entries.byte_offset();
+    }
+}
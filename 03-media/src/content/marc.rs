@@ -0,0 +1,354 @@
+use std::io::Error;
+
+use super::catalog::{Catalog, MediaId};
+use super::import::{format_seconds, parse_seconds, ImportReport};
+use super::media::Media;
+use super::metadata::{Identifier, Metadata, Rating};
+
+/*
+    ### MARC-lite ###
+    - A cut-down take on the MarcEdit '.mrk' text layout:
+    one '=TAG  value' field per line, records separated by
+    a blank line
+        =LDR  Movie
+        =001  3
+        =024  IMDB:tt0816692
+        =100  A director
+        =245  Interstellar
+        =264  2014
+
+    - Tags
+        LDR kind (required)      001 id
+        020 ISBN                 024 other identifier
+        041 language             100 author / director
+        245 title                264 year
+        306 duration (seconds)   362 episode number
+        363 season               490 podcast show / series
+        590 rating               655 genre
+        690 tag
+    - 020, 024, 590, 655 and 690 may repeat
+    - There are no subfields or indicators; '\n', '\r' and
+    '\\' stand for a newline, a carriage return and a
+    backslash in values
+    - 020 and 024 read back in the order they were written,
+    so identifiers keep theirs
+*/
+const REPEATABLE: [&str; 5] = ["020", "024", "590", "655", "690"];
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                text.push('\n');
+                chars.next();
+            }
+            ('\\', Some('r')) => {
+                text.push('\r');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                text.push('\\');
+                chars.next();
+            }
+            (c, _) => text.push(c),
+        }
+    }
+
+    text
+}
+
+fn to_record(id: MediaId, media: &Media) -> String {
+    let mut fields: Vec<(&str, String)> = vec![
+        ("LDR", format!("{:?}", media.kind())),
+        ("001", id.0.to_string()),
+    ];
+
+    if let Some(metadata) = media.metadata() {
+        for identifier in &metadata.identifiers {
+            match identifier {
+                Identifier::Isbn(isbn) => fields.push(("020", isbn.clone())),
+                identifier => fields.push(("024", identifier.to_text())),
+            }
+        }
+        if let Some(language) = &metadata.language {
+            fields.push(("041", language.clone()));
+        }
+    }
+
+    match media {
+        Media::AudioBook { author, title, .. } | Media::Book { author, title, .. } => {
+            fields.push(("100", author.clone()));
+            fields.push(("245", title.clone()));
+        }
+        Media::Movie {
+            director, title, ..
+        } => {
+            fields.push(("100", director.clone()));
+            fields.push(("245", title.clone()));
+        }
+        Media::Podcast {
+            show,
+            season,
+            episode_number,
+            episode_title,
+            ..
+        } => {
+            fields.push(("245", episode_title.clone()));
+            fields.push(("362", episode_number.to_string()));
+            if let Some(season) = season {
+                fields.push(("363", season.to_string()));
+            }
+            fields.push(("490", show.clone()));
+        }
+        Media::TvEpisode {
+            series,
+            season,
+            episode_number,
+            episode_title,
+            ..
+        } => {
+            fields.push(("245", episode_title.clone()));
+            fields.push(("362", episode_number.to_string()));
+            fields.push(("363", season.to_string()));
+            fields.push(("490", series.clone()));
+        }
+        Media::Placeholder => {}
+    }
+
+    if let Some(metadata) = media.metadata() {
+        if let Some(year) = metadata.year {
+            fields.push(("264", year.to_string()));
+        }
+        if let Some(duration) = metadata.duration {
+            fields.push(("306", format_seconds(duration)));
+        }
+        for rating in &metadata.ratings {
+            fields.push(("590", rating.to_text()));
+        }
+        for genre in &metadata.genres {
+            fields.push(("655", genre.clone()));
+        }
+        for tag in &metadata.tags {
+            fields.push(("690", tag.clone()));
+        }
+    }
+
+    fields
+        .iter()
+        .map(|(tag, value)| format!("={}  {}", tag, escape(value)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// (tag, value) pairs of one record, in file order
+struct Record {
+    fields: Vec<(String, String)>,
+}
+
+// Inherent implementation
+impl Record {
+    fn parse(lines: &[&str]) -> Result<Self, Error> {
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        for line in lines {
+            let (tag, value) = line
+                .strip_prefix('=')
+                .filter(|rest| rest.get(3..5) == Some("  "))
+                .map(|rest| (&rest[..3], &rest[5..]))
+                .ok_or_else(|| Error::other(format!("'{}' is not '=TAG  value'", line)))?;
+
+            if !REPEATABLE.contains(&tag) && fields.iter().any(|(seen, _)| seen == tag) {
+                return Err(Error::other(format!("field {} repeated", tag)));
+            }
+            fields.push((tag.to_string(), unescape(value)));
+        }
+
+        Ok(Record { fields })
+    }
+
+    fn all(&self, tag: &str) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|(field, _)| field == tag)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn one(&self, tag: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn required(&self, tag: &str) -> Result<String, Error> {
+        self.one(tag)
+            .map(String::from)
+            .ok_or_else(|| Error::other(format!("missing field {}", tag)))
+    }
+
+    fn id(&self) -> Result<Option<MediaId>, Error> {
+        self.one("001")
+            .map(|id| {
+                id.parse()
+                    .map(MediaId)
+                    .map_err(|_| Error::other(format!("invalid id '{}'", id)))
+            })
+            .transpose()
+    }
+
+    fn to_media(&self) -> Result<Media, Error> {
+        let kind = self.required("LDR")?;
+
+        let own: &[&str] = match kind.as_str() {
+            "AudioBook" | "Book" | "Movie" => &["100", "245"],
+            "Podcast" => &["245", "362", "363", "490"],
+            "TvEpisode" => &["245", "362", "363", "490"],
+            "Placeholder" => &[],
+            _ => return Err(Error::other(format!("unknown kind '{}'", kind))),
+        };
+
+        let metadata_tags = ["020", "024", "041", "264", "306", "590", "655", "690"];
+        for (tag, _) in &self.fields {
+            let allowed = tag == "LDR"
+                || tag == "001"
+                || own.contains(&tag.as_str())
+                || (kind != "Placeholder" && metadata_tags.contains(&tag.as_str()));
+            if !allowed {
+                return Err(Error::other(format!(
+                    "field {} doesn't belong to a {}",
+                    tag, kind
+                )));
+            }
+        }
+
+        Ok(match kind.as_str() {
+            "AudioBook" => Media::AudioBook {
+                author: self.required("100")?,
+                title: self.required("245")?,
+                metadata: self.metadata()?,
+            },
+            "Book" => Media::Book {
+                author: self.required("100")?,
+                title: self.required("245")?,
+                metadata: self.metadata()?,
+            },
+            "Movie" => Media::Movie {
+                director: self.required("100")?,
+                title: self.required("245")?,
+                metadata: self.metadata()?,
+            },
+            "Podcast" => Media::Podcast {
+                show: self.required("490")?,
+                season: self.one("363").map(|_| self.number("363")).transpose()?,
+                episode_number: self.number("362")?,
+                episode_title: self.required("245")?,
+                metadata: self.metadata()?,
+            },
+            "TvEpisode" => Media::TvEpisode {
+                series: self.required("490")?,
+                season: self.number("363")?,
+                episode_number: self.number("362")?,
+                episode_title: self.required("245")?,
+                metadata: self.metadata()?,
+            },
+            _ => Media::Placeholder,
+        })
+    }
+
+    fn number(&self, tag: &str) -> Result<u32, Error> {
+        let value = self.required(tag)?;
+        value
+            .parse()
+            .map_err(|_| Error::other(format!("invalid number '{}' in field {}", value, tag)))
+    }
+
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let mut identifiers = Vec::new();
+        for (tag, value) in &self.fields {
+            match tag.as_str() {
+                "020" => identifiers.push(Identifier::Isbn(value.clone())),
+                "024" => identifiers.push(Identifier::from_text(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(Metadata {
+            year: self
+                .one("264")
+                .map(|year| {
+                    year.parse()
+                        .map_err(|_| Error::other(format!("invalid year '{}'", year)))
+                })
+                .transpose()?,
+            genres: self.all("655"),
+            duration: self.one("306").map(parse_seconds).transpose()?,
+            identifiers,
+            language: self.one("041").map(String::from),
+            ratings: self
+                .all("590")
+                .iter()
+                .map(|text| Rating::from_text(text))
+                .collect::<Result<_, _>>()?,
+            tags: self.all("690"),
+        })
+    }
+}
+
+// Inherent implementation
+impl Catalog {
+    pub fn to_marc(&self) -> String {
+        let records: Vec<String> = self
+            .iter()
+            .map(|(id, media)| to_record(id, media))
+            .collect();
+
+        records.join("\n\n") + "\n"
+    }
+
+    // Bad records are reported with the line of their first
+    // field
+    pub fn import_marc(&mut self, text: &str) -> ImportReport {
+        let mut report = ImportReport::default();
+        let mut record: Vec<&str> = Vec::new();
+        let mut start = 0;
+
+        // A trailing blank line closes the last record
+        for (number, line) in text.lines().chain([""]).enumerate() {
+            if !line.trim().is_empty() {
+                if record.is_empty() {
+                    start = number + 1;
+                }
+                record.push(line);
+                continue;
+            }
+
+            if record.is_empty() {
+                continue;
+            }
+
+            let row = record.join(" | ");
+            let parsed =
+                Record::parse(&record).and_then(|parsed| Ok((parsed.id()?, parsed.to_media()?)));
+
+            match parsed {
+                Ok((id, media)) => self.import_entry(&mut report, start, &row, id, media),
+                Err(error) => report.reject(start, &row, &error.to_string()),
+            }
+
+            record.clear();
+        }
+
+        report
+    }
+}
@@ -1,12 +1,73 @@
-#[derive(Debug)]
+use std::io::Error;
+
+use serde::{Deserialize, Serialize};
+
+use super::metadata::{Identifier, Metadata};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    AudioBook,
+    Book,
+    Movie,
+    Podcast,
+    TvEpisode,
+    Placeholder,
+}
+
+// See 'Media::work_keys'
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WorkKey {
+    Identifier(MediaKind, Identifier),
+    // Normalized title and creator
+    TitleCreator(MediaKind, Option<String>, Option<String>),
+    // Normalized show or series, season, episode number
+    Position(MediaKind, String, Option<u32>, u32),
+}
+
+// Serialized with a "kind" field naming the variant, e.g.
+// {"kind": "Book", "author": .., "title": .., ..}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum Media {
     // AudioBook, Book, Movie etc. are variants of Media
-    AudioBook { title: String },
-    Book { author: String, title: String },
-    Movie { director: String, title: String },
-    // Podcast { episode_number: u32, episode_name: String },
-    // Unnamed fields - This will probably confuse others
-    Podcast(u32, String),
+    AudioBook {
+        author: String,
+        title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    Book {
+        author: String,
+        title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    Movie {
+        director: String,
+        title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    // Named fields - 'Podcast(u32, String)' left everyone
+    // guessing what the number meant
+    Podcast {
+        show: String,
+        // Many shows don't number their seasons
+        #[serde(default)]
+        season: Option<u32>,
+        episode_number: u32,
+        episode_title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    TvEpisode {
+        series: String,
+        season: u32,
+        episode_number: u32,
+        episode_title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
     Placeholder,
 }
 
@@ -14,38 +75,173 @@ pub enum Media {
 impl Media {
     pub fn description(&self) -> String {
         // ### Method 1 - Tedious ###
-        // if let Media::AudioBook { title } = self {
+        // if let Media::AudioBook { title, .. } = self {
         //     format!("AudioBook: {}", title)
-        // } else if let Media::Book { author, title } = self {
+        // } else if let Media::Book { author, title, .. } = self {
         //     format!("Book: {} - {}", title, author)
-        // } else if let Media::Movie { director, title } = self {
+        // } else if let Media::Movie { director, title, .. } = self {
         //     format!("Movie: {} - {}", title, director)
-        // } else if let Media::Podcast(episode_number, episode_name) = self {
-        //     format!("Podcast: {} - {}", episode_number, episode_name)
+        // } else if let Media::Podcast { show, episode_number, .. } = self {
+        //     format!("Podcast: {} #{}", show, episode_number)
         // } else {
         //     String::from("Media description")
         // }
 
         // ### Method 2 - Pattern matching ###
-        match self {
-            Media::AudioBook { title } => {
-                format!("AudioBook: {}", title)
+        let description = match self {
+            Media::AudioBook { author, title, .. } => {
+                format!("AudioBook: {} - {}", title, author)
             }
-            Media::Book { author, title } => {
+            Media::Book { author, title, .. } => {
                 format!("Book: {} - {}", title, author)
             }
-            Media::Movie { director, title } => {
+            Media::Movie {
+                director, title, ..
+            } => {
                 format!("Movie: {} - {}", title, director)
             }
-            // We can use any name instead of
-            // 'episode_number' and 'episode_name' since we
-            // are using unnamed fields in 'Podcast' variant of Media
-            Media::Podcast(episode_number, episode_name) => {
-                format!("Podcast: {} - {}", episode_number, episode_name)
+            Media::Podcast {
+                show,
+                season: Some(season),
+                episode_number,
+                episode_title,
+                ..
+            } => {
+                format!(
+                    "Podcast: {} S{} #{} - {}",
+                    show, season, episode_number, episode_title
+                )
             }
-            Media::Placeholder => {
-                format!("Placeholder!")
+            Media::Podcast {
+                show,
+                episode_number,
+                episode_title,
+                ..
+            } => {
+                format!("Podcast: {} #{} - {}", show, episode_number, episode_title)
             }
+            Media::TvEpisode {
+                series,
+                season,
+                episode_number,
+                episode_title,
+                ..
+            } => {
+                format!(
+                    "TV: {} S{}E{} - {}",
+                    series, season, episode_number, episode_title
+                )
+            }
+            Media::Placeholder => String::from("Placeholder!"),
+        };
+
+        match self.metadata().map(|metadata| metadata.summary()) {
+            Some(details) if !details.is_empty() => format!("{} ({})", description, details),
+            _ => description,
+        }
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        match self {
+            Media::AudioBook { metadata, .. }
+            | Media::Book { metadata, .. }
+            | Media::Movie { metadata, .. }
+            | Media::Podcast { metadata, .. }
+            | Media::TvEpisode { metadata, .. } => Some(metadata),
+            Media::Placeholder => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        match self.metadata() {
+            Some(metadata) => metadata
+                .validate()
+                .map_err(|error| Error::other(format!("'{}': {}", self.description(), error))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn kind(&self) -> MediaKind {
+        match self {
+            Media::AudioBook { .. } => MediaKind::AudioBook,
+            Media::Book { .. } => MediaKind::Book,
+            Media::Movie { .. } => MediaKind::Movie,
+            Media::Podcast { .. } => MediaKind::Podcast,
+            Media::TvEpisode { .. } => MediaKind::TvEpisode,
+            Media::Placeholder => MediaKind::Placeholder,
+        }
+    }
+
+    // For episodes this is the episode title, not the show
+    pub fn title(&self) -> Option<&str> {
+        match self {
+            Media::AudioBook { title, .. }
+            | Media::Book { title, .. }
+            | Media::Movie { title, .. } => Some(title),
+            Media::Podcast { episode_title, .. } | Media::TvEpisode { episode_title, .. } => {
+                Some(episode_title)
+            }
+            Media::Placeholder => None,
+        }
+    }
+
+    // Author for (audio) books, director for movies
+    pub fn creator(&self) -> Option<&str> {
+        match self {
+            Media::AudioBook { author, .. } | Media::Book { author, .. } => Some(author),
+            Media::Movie { director, .. } => Some(director),
+            Media::Podcast { .. } | Media::TvEpisode { .. } | Media::Placeholder => None,
+        }
+    }
+
+    // Two items are the same work when they share a key:
+    // same kind and either a shared identifier (ISBN, IMDB,
+    // ...) or the same title and creator (series position
+    // for episodes), ignoring case. Placeholders have no
+    // keys, so they are never duplicates of anything
+    pub fn work_keys(&self) -> Vec<WorkKey> {
+        let kind = self.kind();
+        let Some(metadata) = self.metadata() else {
+            return Vec::new();
+        };
+
+        let mut keys: Vec<WorkKey> = metadata
+            .identifiers
+            .iter()
+            .map(|identifier| WorkKey::Identifier(kind, identifier.clone()))
+            .collect();
+
+        let normalize = |text: &str| text.trim().to_ascii_lowercase();
+        keys.push(match self.series_position() {
+            Some((series, season, number)) => {
+                WorkKey::Position(kind, normalize(series), season, number)
+            }
+            None => WorkKey::TitleCreator(
+                kind,
+                self.title().map(normalize),
+                self.creator().map(normalize),
+            ),
+        });
+
+        keys
+    }
+
+    // (show or series, season, episode number) for episodes
+    pub fn series_position(&self) -> Option<(&str, Option<u32>, u32)> {
+        match self {
+            Media::Podcast {
+                show,
+                season,
+                episode_number,
+                ..
+            } => Some((show, *season, *episode_number)),
+            Media::TvEpisode {
+                series,
+                season,
+                episode_number,
+                ..
+            } => Some((series, Some(*season), *episode_number)),
+            _ => None,
         }
     }
 }
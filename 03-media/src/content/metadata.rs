@@ -0,0 +1,182 @@
+use std::io::Error;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Identifier {
+    Isbn(String),
+    Imdb(String),
+    // Anything else, e.g. an ASIN or a podcast GUID
+    Other { scheme: String, value: String },
+}
+
+// Inherent implementation
+impl Identifier {
+    // 'ISBN:978-..', 'IMDB:tt..', 'EIDR:10.5240/..'; used by
+    // the CSV and MARC-lite formats
+    pub fn to_text(&self) -> String {
+        match self {
+            Identifier::Isbn(isbn) => format!("ISBN:{}", isbn),
+            Identifier::Imdb(imdb) => format!("IMDB:{}", imdb),
+            Identifier::Other { scheme, value } => format!("{}:{}", scheme, value),
+        }
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, Error> {
+        let (scheme, value) = text
+            .split_once(':')
+            .filter(|(scheme, value)| !scheme.is_empty() && !value.is_empty())
+            .ok_or_else(|| Error::other(format!("identifier '{}' is not 'scheme:value'", text)))?;
+
+        Ok(match scheme {
+            "ISBN" => Identifier::Isbn(value.to_string()),
+            "IMDB" => Identifier::Imdb(value.to_string()),
+            _ => Identifier::Other {
+                scheme: scheme.to_string(),
+                value: value.to_string(),
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub source: String,
+    pub score: f32,
+    pub out_of: f32,
+}
+
+// Inherent implementation
+impl Rating {
+    // '8.7/10 IMDb'
+    pub fn to_text(&self) -> String {
+        format!("{}/{} {}", self.score, self.out_of, self.source)
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, Error> {
+        let invalid = || Error::other(format!("rating '{}' is not 'score/out_of source'", text));
+
+        let (score, source) = text.split_once(' ').ok_or_else(invalid)?;
+        let (score, out_of) = score.split_once('/').ok_or_else(invalid)?;
+
+        Ok(Rating {
+            source: source.to_string(),
+            score: score.parse().map_err(|_| invalid())?,
+            out_of: out_of.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+// Everything is optional; a bare title is still valid media
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub year: Option<u16>,
+    pub genres: Vec<String>,
+    pub duration: Option<Duration>,
+    pub identifiers: Vec<Identifier>,
+    // e.g. "English", "de", "ja-JP" - whatever the source
+    // gave us
+    pub language: Option<String>,
+    pub ratings: Vec<Rating>,
+    // Free-form labels from librarians, e.g. "staff-pick"
+    pub tags: Vec<String>,
+}
+
+// Inherent implementation
+impl Metadata {
+    // Tags are matched case-insensitively
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    // "1999, English, 2h 16m, Sci-Fi/Action, IMDB tt0133093,
+    // 8.7/10 IMDb"; empty when nothing is known
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(year) = self.year {
+            parts.push(year.to_string());
+        }
+
+        if let Some(language) = &self.language {
+            parts.push(language.clone());
+        }
+
+        if let Some(duration) = self.duration {
+            parts.push(format_duration(duration));
+        }
+
+        if !self.genres.is_empty() {
+            parts.push(self.genres.join("/"));
+        }
+
+        for identifier in &self.identifiers {
+            parts.push(match identifier {
+                Identifier::Isbn(isbn) => format!("ISBN {}", isbn),
+                Identifier::Imdb(imdb) => format!("IMDB {}", imdb),
+                Identifier::Other { scheme, value } => format!("{} {}", scheme, value),
+            });
+        }
+
+        for rating in &self.ratings {
+            parts.push(format!(
+                "{}/{} {}",
+                rating.score, rating.out_of, rating.source
+            ));
+        }
+
+        parts.join(", ")
+    }
+
+    // Refuses what the CSV and MARC-lite text forms can't
+    // read back as written: empty list items or language,
+    // an 'Other' identifier that reads back as ISBN/IMDB (or
+    // not at all), a NaN rating
+    pub fn validate(&self) -> Result<(), Error> {
+        for (list, items) in [("genre", &self.genres), ("tag", &self.tags)] {
+            if items.iter().any(|item| item.is_empty()) {
+                return Err(Error::other(format!("empty {}", list)));
+            }
+        }
+
+        if self.language.as_deref() == Some("") {
+            return Err(Error::other("empty language"));
+        }
+
+        for identifier in &self.identifiers {
+            let text = identifier.to_text();
+            if Identifier::from_text(&text).ok().as_ref() != Some(identifier) {
+                return Err(Error::other(format!(
+                    "identifier '{}' wouldn't read back as written",
+                    text
+                )));
+            }
+        }
+
+        for rating in &self.ratings {
+            let text = rating.to_text();
+            if Rating::from_text(&text).ok().as_ref() != Some(rating) {
+                return Err(Error::other(format!(
+                    "rating '{}' wouldn't read back as written",
+                    text
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// '2h 16m', '45m', '30s'
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
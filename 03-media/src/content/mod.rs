@@ -11,6 +11,21 @@
 // 'pub' keyword exports module; 'mod' keyword imports
 // module
 pub mod catalog;
+pub mod circulation;
+pub mod csv_io;
+pub mod duplicates;
 pub mod employee;
+pub mod import;
+pub mod index;
+pub mod json_io;
+pub mod marc;
 pub mod media;
+pub mod metadata;
+pub mod query;
+pub mod recommend;
+pub mod render;
+pub mod scanner;
+pub mod series;
+pub mod store;
 pub mod task;
+pub mod view;
@@ -0,0 +1,101 @@
+use super::catalog::{Catalog, MediaId};
+use super::media::{Media, MediaKind};
+
+/*
+    ### Query ###
+    - Leaves test one property of an item; 'All' and 'Any'
+    combine other queries with AND / OR
+    - Text matching is case-insensitive; title and creator
+    match on substrings, tags must match exactly
+    - Years are inclusive on both ends; items without a
+    year never match a year range
+*/
+#[derive(Debug, Clone)]
+pub enum Query {
+    Title(String),
+    Kind(MediaKind),
+    Creator(String),
+    Years { from: Option<u16>, to: Option<u16> },
+    Tag(String),
+    All(Vec<Query>),
+    Any(Vec<Query>),
+}
+
+// Inherent implementation
+impl Query {
+    pub fn title(text: &str) -> Self {
+        Query::Title(text.to_string())
+    }
+
+    pub fn kind(kind: MediaKind) -> Self {
+        Query::Kind(kind)
+    }
+
+    pub fn creator(name: &str) -> Self {
+        Query::Creator(name.to_string())
+    }
+
+    pub fn years(from: Option<u16>, to: Option<u16>) -> Self {
+        Query::Years { from, to }
+    }
+
+    pub fn tag(tag: &str) -> Self {
+        Query::Tag(tag.to_string())
+    }
+
+    // Flattens 'a.and(b).and(c)' into a single 'All'
+    pub fn and(self, other: Query) -> Self {
+        match self {
+            Query::All(mut queries) => {
+                queries.push(other);
+                Query::All(queries)
+            }
+            query => Query::All(vec![query, other]),
+        }
+    }
+
+    pub fn or(self, other: Query) -> Self {
+        match self {
+            Query::Any(mut queries) => {
+                queries.push(other);
+                Query::Any(queries)
+            }
+            query => Query::Any(vec![query, other]),
+        }
+    }
+
+    pub fn matches(&self, media: &Media) -> bool {
+        match self {
+            Query::Title(text) => media
+                .title()
+                .is_some_and(|title| title.to_lowercase().contains(&text.to_lowercase())),
+            Query::Kind(kind) => media.kind() == *kind,
+            Query::Creator(name) => media
+                .creator()
+                .is_some_and(|creator| creator.to_lowercase().contains(&name.to_lowercase())),
+            Query::Years { from, to } => media
+                .metadata()
+                .and_then(|metadata| metadata.year)
+                .is_some_and(|year| {
+                    from.is_none_or(|from| year >= from) && to.is_none_or(|to| year <= to)
+                }),
+            Query::Tag(tag) => media
+                .metadata()
+                .is_some_and(|metadata| metadata.has_tag(tag)),
+            Query::All(queries) => queries.iter().all(|query| query.matches(media)),
+            Query::Any(queries) => queries.iter().any(|query| query.matches(media)),
+        }
+    }
+}
+
+// Inherent implementation
+impl Catalog {
+    // Borrows matching items in catalog order; nothing is
+    // cloned
+    pub fn search<'a>(
+        &'a self,
+        query: &'a Query,
+    ) -> impl Iterator<Item = (MediaId, &'a Media)> + 'a {
+        self.iter().filter(move |(_, media)| query.matches(media))
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use super::catalog::{Catalog, MediaId};
+use super::media::{Media, MediaKind};
+
+// Who consumed what; the basis for "people who read X
+// also read Y"
+#[derive(Debug, Default)]
+pub struct ConsumptionLog {
+    histories: HashMap<u32, Vec<MediaId>>,
+}
+
+// Inherent implementation
+impl ConsumptionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Consuming the same item twice counts once
+    pub fn record(&mut self, user_id: u32, media_id: MediaId) {
+        let history = self.histories.entry(user_id).or_default();
+        if !history.contains(&media_id) {
+            history.push(media_id);
+        }
+    }
+
+    pub fn history(&self, user_id: u32) -> &[MediaId] {
+        self.histories
+            .get(&user_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn consumers(&self, media_id: MediaId) -> HashSet<u32> {
+        self.histories
+            .iter()
+            .filter(|(_, history)| history.contains(&media_id))
+            .map(|(user_id, _)| *user_id)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    SameCreator(String),
+    SharedGenres(Vec<String>),
+    SameSeries(String),
+    // 'users' consumed both items
+    AlsoConsumed { users: usize },
+}
+
+// One piece of evidence linking a suggestion to an item
+// the user already consumed
+#[derive(Debug, Clone)]
+pub struct Reason {
+    pub because_of: MediaId,
+    pub signal: Signal,
+    pub score: f64,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.signal {
+            Signal::SameCreator(creator) => write!(f, "also by {}", creator)?,
+            Signal::SharedGenres(genres) => write!(f, "also {}", genres.join("/"))?,
+            Signal::SameSeries(series) => write!(f, "more of {}", series)?,
+            Signal::AlsoConsumed { users } => write!(f, "{} other(s) enjoyed both", users)?,
+        }
+
+        write!(f, " (like {}, +{:.2})", self.because_of, self.score)
+    }
+}
+
+#[derive(Debug)]
+pub struct Recommendation<'a> {
+    pub id: MediaId,
+    pub media: &'a Media,
+    pub score: f64,
+    // Strongest first
+    pub reasons: Vec<Reason>,
+}
+
+/*
+    ### Scoring ###
+    - Each candidate is compared with every item the user
+    already consumed ("seeds"); the scores of all matching
+    signals are added up
+        - same author / director: 'creator'
+        - shared genres: 'genre' x Jaccard overlap of the
+        two genre sets
+        - same show / series: 'series'
+        - co-consumption: 'co_consumption' x cosine
+        similarity of the two items' consumers
+    - Already consumed items and placeholders are never
+    suggested
+*/
+#[derive(Debug, Clone)]
+pub struct Weights {
+    pub creator: f64,
+    pub genre: f64,
+    pub series: f64,
+    pub co_consumption: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            creator: 3.0,
+            genre: 2.0,
+            series: 2.5,
+            co_consumption: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Recommender {
+    pub weights: Weights,
+}
+
+// Inherent implementation
+impl Recommender {
+    pub fn new(weights: Weights) -> Self {
+        Recommender { weights }
+    }
+
+    pub fn for_user<'a>(
+        &self,
+        catalog: &'a Catalog,
+        log: &ConsumptionLog,
+        user_id: u32,
+        limit: usize,
+    ) -> Vec<Recommendation<'a>> {
+        self.recommend(catalog, log, log.history(user_id), limit)
+    }
+
+    // "More like this" for a single item
+    pub fn more_like<'a>(
+        &self,
+        catalog: &'a Catalog,
+        log: &ConsumptionLog,
+        media_id: MediaId,
+        limit: usize,
+    ) -> Vec<Recommendation<'a>> {
+        self.recommend(catalog, log, &[media_id], limit)
+    }
+
+    fn recommend<'a>(
+        &self,
+        catalog: &'a Catalog,
+        log: &ConsumptionLog,
+        seeds: &[MediaId],
+        limit: usize,
+    ) -> Vec<Recommendation<'a>> {
+        let seeds: Vec<(MediaId, &Media, HashSet<u32>)> = seeds
+            .iter()
+            .filter_map(|id| Some((*id, catalog.get(*id)?, log.consumers(*id))))
+            .collect();
+        let seed_ids: HashSet<MediaId> = seeds.iter().map(|(id, _, _)| *id).collect();
+
+        let mut recommendations: Vec<Recommendation> = catalog
+            .iter()
+            .filter(|(id, media)| !seed_ids.contains(id) && media.kind() != MediaKind::Placeholder)
+            .filter_map(|(id, media)| {
+                let consumers = log.consumers(id);
+
+                let mut reasons: Vec<Reason> = seeds
+                    .iter()
+                    .flat_map(|(seed_id, seed, seed_consumers)| {
+                        self.signals(seed, seed_consumers, media, &consumers)
+                            .into_iter()
+                            .map(|(signal, score)| Reason {
+                                because_of: *seed_id,
+                                signal,
+                                score,
+                            })
+                    })
+                    .collect();
+
+                if reasons.is_empty() {
+                    return None;
+                }
+
+                reasons.sort_by(|a, b| b.score.total_cmp(&a.score));
+                Some(Recommendation {
+                    id,
+                    media,
+                    score: reasons.iter().map(|reason| reason.score).sum(),
+                    reasons,
+                })
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        recommendations.truncate(limit);
+        recommendations
+    }
+
+    fn signals(
+        &self,
+        seed: &Media,
+        seed_consumers: &HashSet<u32>,
+        candidate: &Media,
+        candidate_consumers: &HashSet<u32>,
+    ) -> Vec<(Signal, f64)> {
+        let mut signals = Vec::new();
+
+        if let (Some(a), Some(b)) = (seed.creator(), candidate.creator()) {
+            if a.eq_ignore_ascii_case(b) {
+                signals.push((Signal::SameCreator(b.to_string()), self.weights.creator));
+            }
+        }
+
+        let genres = |media: &Media| -> BTreeMap<String, String> {
+            media
+                .metadata()
+                .map(|metadata| {
+                    metadata
+                        .genres
+                        .iter()
+                        .map(|genre| (genre.to_lowercase(), genre.clone()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let (a, b) = (genres(seed), genres(candidate));
+        let shared: Vec<String> = b
+            .iter()
+            .filter(|(key, _)| a.contains_key(*key))
+            .map(|(_, genre)| genre.clone())
+            .collect();
+        if !shared.is_empty() {
+            let union = a.len() + b.len() - shared.len();
+            let score = self.weights.genre * shared.len() as f64 / union as f64;
+            signals.push((Signal::SharedGenres(shared), score));
+        }
+
+        if let (Some((a, _, _)), Some((b, _, _))) =
+            (seed.series_position(), candidate.series_position())
+        {
+            if a.eq_ignore_ascii_case(b) {
+                signals.push((Signal::SameSeries(b.to_string()), self.weights.series));
+            }
+        }
+
+        let both = seed_consumers.intersection(candidate_consumers).count();
+        if both > 0 {
+            let cosine =
+                both as f64 / ((seed_consumers.len() * candidate_consumers.len()) as f64).sqrt();
+            signals.push((
+                Signal::AlsoConsumed { users: both },
+                self.weights.co_consumption * cosine,
+            ));
+        }
+
+        signals
+    }
+}
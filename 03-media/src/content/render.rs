@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Error;
+use std::sync::OnceLock;
+
+use super::media::Media;
+
+// Anything that can turn an item into text for people to
+// read; implement it to add a house style of your own
+pub trait Renderer {
+    fn render(&self, media: &Media) -> String;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Short,
+    Long,
+    Apa,
+    Mla,
+}
+
+// Inherent implementation
+impl Style {
+    fn key(&self) -> &'static str {
+        match self {
+            Style::Short => "short",
+            Style::Long => "long",
+            Style::Apa => "apa",
+            Style::Mla => "mla",
+        }
+    }
+}
+
+/*
+    ### Templates ###
+    - Loaded from 'locales/<language>.txt', one
+    'key = template' per line; '#' starts a comment
+    - A style is looked up as '<style>.<Kind>' and then
+    '<style>', first in the locale and then in its
+    fallback, so a locale only needs the keys it changes
+    - English ships inside the binary and is the usual
+    fallback
+*/
+#[derive(Debug, Clone)]
+pub struct Templates {
+    pub language: String,
+    entries: HashMap<String, String>,
+    fallback: Option<Box<Templates>>,
+}
+
+// Inherent implementation
+impl Templates {
+    pub fn english() -> Self {
+        Templates::parse("en", include_str!("../../locales/en.txt"))
+            .expect("built-in English templates are valid")
+    }
+
+    // 'locales/de.txt' -> German, falling back to English
+    pub fn load(dir: &str, language: &str) -> Result<Self, Error> {
+        let path = format!("{}/{}.txt", dir, language);
+        let text = fs::read_to_string(&path)
+            .map_err(|error| Error::other(format!("{}: {}", path, error)))?;
+
+        Ok(Templates::parse(language, &text)?.with_fallback(Templates::english()))
+    }
+
+    pub fn parse(language: &str, text: &str) -> Result<Self, Error> {
+        let mut entries = HashMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, template) = line.split_once('=').ok_or_else(|| {
+                Error::other(format!(
+                    "{} line {}: expected 'key = template'",
+                    language,
+                    number + 1
+                ))
+            })?;
+            entries.insert(key.trim().to_string(), template.trim().to_string());
+        }
+
+        Ok(Templates {
+            language: language.to_string(),
+            entries,
+            fallback: None,
+        })
+    }
+
+    pub fn with_fallback(mut self, fallback: Templates) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    fn own(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.own(key).or_else(|| self.fallback.as_ref()?.get(key))
+    }
+
+    // '<style>.<Kind>', then '<style>'
+    fn template(&self, style: Style, kind: &str) -> Option<&str> {
+        let specific = format!("{}.{}", style.key(), kind);
+
+        self.own(&specific)
+            .or_else(|| self.own(style.key()))
+            .or_else(|| self.fallback.as_ref()?.template(style, kind))
+    }
+}
+
+// Built-in styles, driven entirely by the templates
+pub struct TemplateRenderer<'a> {
+    pub style: Style,
+    pub templates: &'a Templates,
+}
+
+impl Renderer for TemplateRenderer<'_> {
+    fn render(&self, media: &Media) -> String {
+        let kind = format!("{:?}", media.kind());
+        let values = fields(media, &kind, self.templates);
+
+        match self.templates.template(self.style, &kind) {
+            Some(template) => fill(template, &values),
+            None => media.description(),
+        }
+    }
+}
+
+// Inherent implementation
+impl Media {
+    pub fn render(&self, renderer: &dyn Renderer) -> String {
+        renderer.render(self)
+    }
+}
+
+// '{}' gives the English short style, e.g. "The Hobbit - J.
+// R. R. Tolkien"; use 'render' for anything else
+impl fmt::Display for Media {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        static ENGLISH: OnceLock<Templates> = OnceLock::new();
+
+        let renderer = TemplateRenderer {
+            style: Style::Short,
+            templates: ENGLISH.get_or_init(Templates::english),
+        };
+        f.write_str(&renderer.render(self))
+    }
+}
+
+// "Tolkien, J. R. R." for APA / MLA
+fn invert_name(name: &str) -> String {
+    match name.trim().rsplit_once(' ') {
+        Some((given, family)) => format!("{}, {}", family, given),
+        None => name.trim().to_string(),
+    }
+}
+
+fn fields(media: &Media, kind: &str, templates: &Templates) -> HashMap<&'static str, String> {
+    let mut values = HashMap::new();
+    let template = |key: &str| templates.get(key).unwrap_or_default().to_string();
+
+    values.insert(
+        "kind",
+        templates
+            .get(&format!("kind.{}", kind))
+            .unwrap_or(kind)
+            .to_string(),
+    );
+    values.insert("title", media.title().unwrap_or_default().to_string());
+
+    if let Some(creator) = media.creator() {
+        values.insert("creator", creator.to_string());
+        values.insert("creator_inverted", invert_name(creator));
+    }
+
+    if let Some((series, season, number)) = media.series_position() {
+        values.insert("series", series.to_string());
+        values.insert("episode", number.to_string());
+        if let Some(season) = season {
+            values.insert("season", season.to_string());
+        }
+    }
+
+    if let Some(metadata) = media.metadata() {
+        if let Some(year) = metadata.year {
+            values.insert("year", year.to_string());
+        }
+        if let Some(language) = &metadata.language {
+            values.insert("language", language.clone());
+        }
+        values.insert("genres", metadata.genres.join(&template("list.separator")));
+
+        if let Some(duration) = metadata.duration {
+            let minutes = duration.as_secs() / 60;
+            let mut parts = HashMap::new();
+            parts.insert("hours", (minutes / 60).to_string());
+            parts.insert("minutes", (minutes % 60).to_string());
+
+            let key = if minutes >= 60 {
+                "duration"
+            } else {
+                "duration.minutes"
+            };
+            values.insert("duration", fill(&template(key), &parts));
+        }
+    }
+
+    values
+}
+
+// '{name}', '{name|fallback}' and optional '[...]' groups;
+// see 'locales/en.txt'
+pub fn fill(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut output = String::new();
+    // Text of the open '[...]' group, whether it has any
+    // fields and whether all of them had a value
+    let mut group: Option<(String, bool, bool)> = None;
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        let text = match c {
+            '[' if group.is_none() => {
+                group = Some((String::new(), false, true));
+                continue;
+            }
+            ']' if group.is_some() => {
+                match group.take() {
+                    // '[Film]' - no fields, so it's plain text
+                    Some((text, false, _)) => output.push_str(&format!("[{}]", text)),
+                    Some((text, true, true)) => output.push_str(&text),
+                    _ => {}
+                }
+                continue;
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let (name, default) = match placeholder.split_once('|') {
+                    Some((name, default)) => (name, Some(default)),
+                    None => (placeholder.as_str(), None),
+                };
+
+                let value = values
+                    .get(name)
+                    .filter(|value| !value.is_empty())
+                    .map(String::as_str)
+                    .or(default);
+
+                if let Some((_, has_fields, complete)) = &mut group {
+                    *has_fields = true;
+                    *complete &= value.is_some();
+                }
+                value.unwrap_or_default().to_string()
+            }
+            c => c.to_string(),
+        };
+
+        match &mut group {
+            Some((group_text, _, _)) => group_text.push_str(&text),
+            None => output.push_str(&text),
+        }
+    }
+
+    // An unclosed group is kept as written
+    if let Some((text, _, _)) = group {
+        output.push('[');
+        output.push_str(&text);
+    }
+
+    output
+}
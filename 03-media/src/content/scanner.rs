@@ -0,0 +1,275 @@
+use std::fs::{self, File};
+use std::io::{Error, Read};
+use std::path::{Path, PathBuf};
+
+use lofty::prelude::*;
+use zip::ZipArchive;
+
+use super::catalog::{Catalog, MediaId};
+use super::media::Media;
+use super::metadata::{Identifier, Metadata};
+
+/*
+    ### Scanner ###
+    - Walks a directory tree (in name order) and turns
+    every media file it can read into a catalog item
+        - .epub -> Book (Dublin Core title, creator,
+        language, date, ISBN)
+        - .m4b -> AudioBook
+        - .mp3, .m4a -> Podcast when tagged as one (genre
+        'Podcast'), otherwise AudioBook; the album is the
+        show and the track number the episode
+        - .mp4, .m4v, .mov -> Movie
+    - Anything else (including Matroska and AVI video,
+    which we have no metadata reader for), or a file whose
+    metadata can't be read, is reported instead of added
+    - Files without an embedded title use their file name
+    - Symlinked files are read; symlinked directories are
+    skipped, so a link cycle can't send the walk round
+    forever
+    - A subdirectory that can't be listed is reported and
+    the walk goes on without it
+*/
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub added: Vec<(PathBuf, MediaId)>,
+    // Not a format we know, or not readable as one
+    pub unrecognized: Vec<SkippedFile>,
+    // Read fine but refused by the catalog, e.g. duplicates,
+    // or a directory that couldn't be listed
+    pub rejected: Vec<SkippedFile>,
+}
+
+enum FileKind {
+    Epub,
+    AudioBook,
+    Audio,
+    Video,
+}
+
+fn file_kind(path: &Path) -> Option<FileKind> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    match extension.as_str() {
+        "epub" => Some(FileKind::Epub),
+        "m4b" => Some(FileKind::AudioBook),
+        "mp3" | "m4a" => Some(FileKind::Audio),
+        "mp4" | "m4v" | "mov" => Some(FileKind::Video),
+        _ => None,
+    }
+}
+
+// Every file under 'dir', sorted so scans are repeatable.
+// 'file_type' doesn't follow symlinks, so a link to a
+// directory (maybe one of its own parents) is skipped
+// rather than walked. Only failing to list 'dir' itself is
+// an error; subdirectories that fail go into 'rejected'
+fn walk(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    rejected: &mut Vec<SkippedFile>,
+) -> Result<(), Error> {
+    let mut entries: Vec<(PathBuf, fs::FileType)> = fs::read_dir(dir)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+        .collect::<Result<_, _>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            if let Err(error) = walk(&path, files, rejected) {
+                rejected.push(SkippedFile {
+                    path,
+                    reason: format!("unreadable directory: {}", error),
+                });
+            }
+        } else if !(file_type.is_symlink() && path.is_dir()) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Text of every '<name ...>text</name>' element, in order;
+// good enough for OPF metadata, not a general XML parser
+fn elements_text(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+
+    let mut texts = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+
+        // '<dc:title>' or '<dc:title id="..">', not '<dc:titles>'
+        if !rest.starts_with(['>', ' ', '\t', '\n', '\r']) {
+            continue;
+        }
+
+        let Some(body) = rest.find('>').map(|end| &rest[end + 1..]) else {
+            break;
+        };
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        texts.push(unescape_xml(body[..end].trim()));
+        rest = &body[end + close.len()..];
+    }
+
+    texts
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    elements_text(xml, name).into_iter().next()
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn read_epub(path: &Path) -> Result<Media, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(Error::other)?;
+
+    let mut read_entry = |name: &str| -> Result<String, Error> {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .map_err(|error| Error::other(format!("{}: {}", name, error)))?
+            .read_to_string(&mut text)?;
+        Ok(text)
+    };
+
+    // The container points at the package (OPF) document
+    let container = read_entry("META-INF/container.xml")?;
+    let package_path = container
+        .split("full-path=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .ok_or_else(|| Error::other("container.xml names no package document"))?;
+    let package = read_entry(package_path)?;
+
+    // A package often lists a UUID or a publisher's own id
+    // before the ISBN, or both of an ISBN-10 and -13
+    let mut identifiers: Vec<Identifier> = Vec::new();
+    for id in elements_text(&package, "dc:identifier") {
+        let isbn = id.strip_prefix("urn:isbn:").unwrap_or(&id);
+        let digits = isbn.chars().filter(|c| c.is_ascii_alphanumeric()).count();
+        let identifier = Identifier::Isbn(isbn.to_string());
+        if (digits == 10 || digits == 13) && !identifiers.contains(&identifier) {
+            identifiers.push(identifier);
+        }
+    }
+
+    Ok(Media::Book {
+        author: element_text(&package, "dc:creator").unwrap_or_default(),
+        title: element_text(&package, "dc:title").unwrap_or_else(|| file_stem(path)),
+        metadata: Metadata {
+            year: element_text(&package, "dc:date").and_then(|date| date.get(..4)?.parse().ok()),
+            identifiers,
+            language: element_text(&package, "dc:language"),
+            ..Default::default()
+        },
+    })
+}
+
+fn read_tagged(path: &Path, kind: FileKind) -> Result<Media, Error> {
+    let file = lofty::read_from_path(path).map_err(Error::other)?;
+    let tag = file.primary_tag().or_else(|| file.first_tag());
+
+    let text = |value: Option<String>| value.filter(|value| !value.is_empty());
+    let title =
+        text(tag.and_then(|tag| tag.title().map(String::from))).unwrap_or_else(|| file_stem(path));
+    let artist = text(tag.and_then(|tag| tag.artist().map(String::from)));
+    let genre = text(tag.and_then(|tag| tag.genre().map(String::from)));
+
+    let duration = file.properties().duration();
+    let metadata = Metadata {
+        year: tag.and_then(|tag| tag.date()).map(|date| date.year),
+        genres: genre.clone().into_iter().collect(),
+        duration: (!duration.is_zero()).then_some(duration),
+        ..Default::default()
+    };
+
+    let is_podcast = genre.is_some_and(|genre| genre.eq_ignore_ascii_case("podcast"));
+
+    Ok(match kind {
+        FileKind::Audio if is_podcast => Media::Podcast {
+            show: text(tag.and_then(|tag| tag.album().map(String::from)))
+                .or(artist)
+                .unwrap_or_default(),
+            season: tag.and_then(|tag| tag.disk()),
+            episode_number: tag
+                .and_then(|tag| tag.track())
+                .ok_or_else(|| Error::other("podcast episode has no track number"))?,
+            episode_title: title,
+            metadata,
+        },
+        FileKind::Video => Media::Movie {
+            director: text(tag.and_then(|tag| tag.get_string(ItemKey::Director).map(String::from)))
+                .or(artist)
+                .unwrap_or_default(),
+            title,
+            metadata,
+        },
+        _ => Media::AudioBook {
+            author: artist.unwrap_or_default(),
+            title,
+            metadata,
+        },
+    })
+}
+
+pub fn read_media_file(path: &Path) -> Result<Option<Media>, Error> {
+    match file_kind(path) {
+        Some(FileKind::Epub) => read_epub(path).map(Some),
+        Some(kind) => read_tagged(path, kind).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Inherent implementation
+impl Catalog {
+    pub fn scan_directory(&mut self, dir: &str) -> Result<ScanReport, Error> {
+        let mut report = ScanReport::default();
+        let mut files = Vec::new();
+        walk(Path::new(dir), &mut files, &mut report.rejected)?;
+
+        for path in files {
+            let skipped = |reason: String| SkippedFile {
+                path: path.clone(),
+                reason,
+            };
+
+            match read_media_file(&path) {
+                Ok(Some(media)) => match self.add(media) {
+                    Ok(id) => report.added.push((path, id)),
+                    Err(error) => report.rejected.push(skipped(error.to_string())),
+                },
+                Ok(None) => report
+                    .unrecognized
+                    .push(skipped(String::from("not a supported media file"))),
+                Err(error) => report
+                    .unrecognized
+                    .push(skipped(format!("unreadable: {}", error))),
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::catalog::{Catalog, MediaId};
+use super::media::Media;
+
+// One podcast or TV episode, borrowed from the catalog
+#[derive(Debug)]
+pub struct Episode<'a> {
+    pub id: MediaId,
+    pub season: Option<u32>,
+    pub number: u32,
+    pub media: &'a Media,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Gap {
+    // A whole season between the first and the last is
+    // missing
+    Season(u32),
+    // Catalogs often hold only part of a show, so only
+    // numbers between the lowest and highest held episode of
+    // a season count
+    Episode { season: Option<u32>, number: u32 },
+}
+
+// Inherent implementation
+impl Catalog {
+    // Every show and series name, once, in catalog order
+    pub fn series_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+
+        for (_, media) in self.iter() {
+            if let Some((series, _, _)) = media.series_position() {
+                if !names.iter().any(|name| name.eq_ignore_ascii_case(series)) {
+                    names.push(series);
+                }
+            }
+        }
+
+        names
+    }
+
+    // Ordered by season (unnumbered first), then episode;
+    // the name is matched ignoring case
+    pub fn episodes(&self, series: &str) -> Vec<Episode<'_>> {
+        let mut episodes: Vec<Episode> = self
+            .iter()
+            .filter_map(|(id, media)| {
+                let (name, season, number) = media.series_position()?;
+                name.eq_ignore_ascii_case(series).then_some(Episode {
+                    id,
+                    season,
+                    number,
+                    media,
+                })
+            })
+            .collect();
+
+        episodes.sort_by_key(|episode| (episode.season, episode.number, episode.id));
+        episodes
+    }
+
+    pub fn missing_episodes(&self, series: &str) -> Vec<Gap> {
+        let mut seasons: BTreeMap<Option<u32>, HashSet<u32>> = BTreeMap::new();
+        for episode in self.episodes(series) {
+            seasons
+                .entry(episode.season)
+                .or_default()
+                .insert(episode.number);
+        }
+
+        let mut gaps = Vec::new();
+
+        let numbered: Vec<u32> = seasons.keys().filter_map(|season| *season).collect();
+        if let (Some(first), Some(last)) = (numbered.first(), numbered.last()) {
+            for season in *first..*last {
+                if !numbered.contains(&season) {
+                    gaps.push(Gap::Season(season));
+                }
+            }
+        }
+
+        for (season, numbers) in &seasons {
+            let first = numbers.iter().min().copied().unwrap_or(0);
+            let last = numbers.iter().max().copied().unwrap_or(0);
+            for number in first..last {
+                if !numbers.contains(&number) {
+                    gaps.push(Gap::Episode {
+                        season: *season,
+                        number,
+                    });
+                }
+            }
+        }
+
+        gaps
+    }
+
+    // The episode after the furthest one played, even if
+    // earlier ones were skipped; the first episode if none
+    // were played and 'None' once the last one was
+    pub fn next_unplayed(&self, series: &str, played: &HashSet<MediaId>) -> Option<Episode<'_>> {
+        let episodes = self.episodes(series);
+
+        let next = match episodes
+            .iter()
+            .rposition(|episode| played.contains(&episode.id))
+        {
+            Some(position) => position + 1,
+            None => 0,
+        };
+
+        episodes.into_iter().nth(next)
+    }
+}
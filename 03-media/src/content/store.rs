@@ -0,0 +1,438 @@
+use std::io::Error;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use super::catalog::{Catalog, MediaId};
+use super::duplicates::DuplicateIndex;
+use super::media::Media;
+
+/*
+    ### Schema migrations ###
+        - Each item is stored as its JSON form (the same one
+        'to_json' writes) next to a plain 'kind' column;
+        duplicate checks only read the rows of the new
+        item's kind, straight from the table, so rows
+        other connections wrote count too
+        - The database records how many migrations it has
+        run in 'PRAGMA user_version'; 'open' runs the rest,
+        each in its own transaction
+        - Never edit a migration that has shipped - append a
+        new one instead
+        - A new field on a 'Media' variant needs
+        '#[serde(default)]' so rows written before it still
+        load; a renamed field or variant needs a migration
+        that rewrites 'data' (see SQLite's 'json_set')
+        - A database written by a newer build (higher
+        'user_version') is refused rather than guessed at
+*/
+const MIGRATIONS: [&str; 2] = [
+    // 1: one row per item; AUTOINCREMENT keeps ids from
+    // being reused after a delete, like 'Catalog' does
+    "CREATE TABLE media (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        data TEXT NOT NULL
+    );",
+    // 2: duplicate checks select by kind
+    "CREATE INDEX media_kind ON media (kind);",
+];
+
+// A row 'load' couldn't bring back; the rest still load
+#[derive(Debug)]
+pub struct SkippedRow {
+    pub id: MediaId,
+    pub reason: String,
+}
+
+pub struct CatalogStore {
+    connection: Connection,
+}
+
+// Inherent implementation
+impl CatalogStore {
+    // Creates the file if needed and brings its schema up
+    // to date
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let connection = Connection::open(path).map_err(Error::other)?;
+        let mut store = CatalogStore { connection };
+        store.migrate()?;
+
+        Ok(store)
+    }
+
+    pub fn schema_version(&self) -> Result<u32, Error> {
+        self.connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(Error::other)
+    }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        let version = self.schema_version()? as usize;
+        if version > MIGRATIONS.len() {
+            return Err(Error::other(format!(
+                "database schema version {} is newer than this build supports ({})",
+                version,
+                MIGRATIONS.len()
+            )));
+        }
+
+        for (number, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = self.connection.transaction().map_err(Error::other)?;
+            transaction
+                .execute_batch(migration)
+                .and_then(|_| transaction.pragma_update(None, "user_version", number as u32 + 1))
+                .and_then(|_| transaction.commit())
+                .map_err(|error| {
+                    Error::other(format!("migration {} failed: {}", number + 1, error))
+                })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> Result<usize, Error> {
+        self.connection
+            .query_row("SELECT COUNT(*) FROM media", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(Error::other)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    // Same rules as 'Catalog::add': duplicates of an item
+    // already stored are refused. The check and the insert
+    // share a write transaction, so no other connection
+    // can slip the same item in between them
+    pub fn add(&mut self, media: Media) -> Result<MediaId, Error> {
+        media.validate()?;
+
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::other)?;
+        check_duplicates(&transaction, &media, None)?;
+
+        let data = serde_json::to_string(&media).map_err(Error::other)?;
+        transaction
+            .execute(
+                "INSERT INTO media (kind, data) VALUES (?1, ?2)",
+                params![format!("{:?}", media.kind()), data],
+            )
+            .map_err(Error::other)?;
+        let id = MediaId(transaction.last_insert_rowid() as u64);
+        transaction.commit().map_err(Error::other)?;
+
+        Ok(id)
+    }
+
+    // Unlike 'Catalog::get' this hands back an owned copy -
+    // the item lives in the database, not in memory
+    pub fn get(&self, id: MediaId) -> Result<Option<Media>, Error> {
+        fetch(&self.connection, id)
+    }
+
+    // Same rules as 'Catalog::update': the id stays the
+    // same and the previous item comes back
+    pub fn update(&mut self, id: MediaId, media: Media) -> Result<Media, Error> {
+        media.validate()?;
+
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::other)?;
+        let previous = fetch(&transaction, id)?
+            .ok_or_else(|| Error::other(format!("no media with id {}", id)))?;
+        check_duplicates(&transaction, &media, Some(id))?;
+
+        let data = serde_json::to_string(&media).map_err(Error::other)?;
+        transaction
+            .execute(
+                "UPDATE media SET kind = ?1, data = ?2 WHERE id = ?3",
+                params![format!("{:?}", media.kind()), data, id.0 as i64],
+            )
+            .map_err(Error::other)?;
+        transaction.commit().map_err(Error::other)?;
+
+        Ok(previous)
+    }
+
+    pub fn remove(&mut self, id: MediaId) -> Result<Option<Media>, Error> {
+        let media = self.get(id)?;
+        self.connection
+            .execute("DELETE FROM media WHERE id = ?1", params![id.0 as i64])
+            .map_err(Error::other)?;
+
+        Ok(media)
+    }
+
+    // Everything in the store, with the same ids, for the
+    // in-memory search, views and exports. A row that no
+    // longer reads back or passes today's checks is skipped
+    // and reported instead of failing the whole load
+    pub fn load(&self) -> Result<(Catalog, Vec<SkippedRow>), Error> {
+        let mut catalog = Catalog::new();
+        let mut skipped = Vec::new();
+        for (id, data) in rows(
+            &self.connection,
+            "SELECT id, data FROM media ORDER BY id",
+            [],
+        )? {
+            let added = decode(id, &data).and_then(|media| catalog.add_with_id(id, media));
+            if let Err(error) = added {
+                skipped.push(SkippedRow {
+                    id,
+                    reason: error.to_string(),
+                });
+            }
+        }
+
+        Ok((catalog, skipped))
+    }
+}
+
+fn fetch(connection: &Connection, id: MediaId) -> Result<Option<Media>, Error> {
+    let data: Option<String> = connection
+        .query_row(
+            "SELECT data FROM media WHERE id = ?1",
+            params![id.0 as i64],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(Error::other)?;
+
+    data.map(|data| decode(id, &data)).transpose()
+}
+
+// Every work key includes the kind, so only rows of the
+// same kind can be duplicates. Rows that don't decode
+// can't be compared and are left to 'load' to report
+fn check_duplicates(
+    connection: &Connection,
+    media: &Media,
+    ignore: Option<MediaId>,
+) -> Result<(), Error> {
+    let mut duplicates = DuplicateIndex::default();
+    let candidates = rows(
+        connection,
+        "SELECT id, data FROM media WHERE kind = ?1",
+        params![format!("{:?}", media.kind())],
+    )?;
+    for (id, data) in candidates {
+        if let Ok(candidate) = decode(id, &data) {
+            duplicates.insert(id, &candidate);
+        }
+    }
+
+    match duplicates.find(media, ignore) {
+        Some(existing) => Err(Error::other(format!(
+            "'{}' duplicates {}",
+            media.description(),
+            existing
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn rows<P: rusqlite::Params>(
+    connection: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<Vec<(MediaId, String)>, Error> {
+    let mut statement = connection.prepare(sql).map_err(Error::other)?;
+    let rows = statement
+        .query_map(params, |row| {
+            Ok((
+                MediaId(row.get::<_, i64>(0)? as u64),
+                row.get::<_, String>(1)?,
+            ))
+        })
+        .map_err(Error::other)?;
+
+    rows.map(|row| row.map_err(Error::other)).collect()
+}
+
+fn decode(id: MediaId, data: &str) -> Result<Media, Error> {
+    serde_json::from_str(data).map_err(|error| {
+        Error::other(format!(
+            "can't read item {} (written by a newer build?): {}",
+            id, error
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::metadata::Metadata;
+
+    fn book(title: &str) -> Media {
+        Media::Book {
+            author: String::from("An author"),
+            title: String::from(title),
+            metadata: Metadata::default(),
+        }
+    }
+
+    // A fresh file per test, so tests can run side by side
+    fn path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("media-store-{}.db", name));
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+        path
+    }
+
+    // What a build with only the first migration left behind
+    fn version_1(path: &Path, items: &[Media]) {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection.pragma_update(None, "user_version", 1).unwrap();
+        for media in items {
+            connection
+                .execute(
+                    "INSERT INTO media (kind, data) VALUES (?1, ?2)",
+                    params![
+                        format!("{:?}", media.kind()),
+                        serde_json::to_string(media).unwrap()
+                    ],
+                )
+                .unwrap();
+        }
+    }
+
+    fn descriptions(catalog: &Catalog) -> Vec<(MediaId, String)> {
+        catalog
+            .iter()
+            .map(|(id, media)| (id, media.description()))
+            .collect()
+    }
+
+    #[test]
+    fn reopening_a_version_1_database_migrates_it() {
+        let path = path("version-1");
+        version_1(&path, &[book("First"), book("Second")]);
+
+        let store = CatalogStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len() as u32);
+
+        let index: Option<String> = store
+            .connection
+            .query_row(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'media_kind'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap();
+        assert_eq!(index.as_deref(), Some("media_kind"));
+
+        let (catalog, skipped) = store.load().unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            descriptions(&catalog),
+            vec![
+                (MediaId(1), book("First").description()),
+                (MediaId(2), book("Second").description()),
+            ]
+        );
+    }
+
+    #[test]
+    fn items_round_trip() {
+        let path = path("round-trip");
+        let mut store = CatalogStore::open(&path).unwrap();
+        let first = store.add(book("First")).unwrap();
+        let second = store.add(book("Second")).unwrap();
+        drop(store);
+
+        let store = CatalogStore::open(&path).unwrap();
+        assert_eq!(
+            store.get(second).unwrap().map(|media| media.description()),
+            Some(book("Second").description())
+        );
+        let (catalog, _) = store.load().unwrap();
+        assert_eq!(
+            descriptions(&catalog),
+            vec![
+                (first, book("First").description()),
+                (second, book("Second").description()),
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let path = path("newer");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() as u32 + 1)
+            .unwrap();
+
+        assert!(CatalogStore::open(&path).is_err());
+    }
+
+    #[test]
+    fn duplicates_written_by_another_connection_are_refused() {
+        let path = path("two-connections");
+        let mut store = CatalogStore::open(&path).unwrap();
+        store.add(book("First")).unwrap();
+
+        let mut other = CatalogStore::open(&path).unwrap();
+        let second = other.add(book("Second")).unwrap();
+
+        let error = store.add(book("Second")).unwrap_err();
+        assert!(error.to_string().contains(&second.to_string()));
+        assert_eq!(store.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn update_keeps_the_id_and_checks_duplicates() {
+        let path = path("update");
+        let mut store = CatalogStore::open(&path).unwrap();
+        let first = store.add(book("First")).unwrap();
+        store.add(book("Second")).unwrap();
+
+        let previous = store.update(first, book("Third")).unwrap();
+        assert_eq!(previous.description(), book("First").description());
+        assert_eq!(
+            store.get(first).unwrap().map(|media| media.description()),
+            Some(book("Third").description())
+        );
+
+        // Unchanged is not a duplicate of itself
+        assert!(store.update(first, book("Third")).is_ok());
+        assert!(store.update(first, book("Second")).is_err());
+        assert!(store.update(MediaId(99), book("Fourth")).is_err());
+        assert_eq!(store.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn load_skips_and_reports_bad_rows() {
+        let path = path("bad-rows");
+        version_1(&path, &[book("First"), book("First"), book("Second")]);
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute(
+                "INSERT INTO media (kind, data) VALUES ('Book', '{\"kind\": \"Scroll\"}')",
+                [],
+            )
+            .unwrap();
+        drop(connection);
+
+        let store = CatalogStore::open(&path).unwrap();
+        let (catalog, skipped) = store.load().unwrap();
+
+        assert_eq!(
+            descriptions(&catalog),
+            vec![
+                (MediaId(1), book("First").description()),
+                (MediaId(3), book("Second").description()),
+            ]
+        );
+        let skipped: Vec<_> = skipped.iter().map(|row| row.id).collect();
+        assert_eq!(skipped, vec![MediaId(2), MediaId(4)]);
+    }
+}
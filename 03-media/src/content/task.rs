@@ -1,7 +1,319 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+
+use chrono::NaiveDate;
+
 // 'super' is the reference to the parent module
 use super::employee::Employee;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Todo,
+    InProgress,
+    Done,
+}
+
+// Inherent implementation
+impl TaskStatus {
+    // Todo -> InProgress -> Done; work can be paused back to
+    // Todo and finished work reopened
+    pub fn can_transition_to(&self, next: TaskStatus) -> bool {
+        match self {
+            TaskStatus::Todo => next == TaskStatus::InProgress,
+            TaskStatus::InProgress => matches!(next, TaskStatus::Todo | TaskStatus::Done),
+            TaskStatus::Done => next == TaskStatus::InProgress,
+        }
+    }
+}
+
+// Declared lowest first so 'Ord' sorts by urgency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+// 'None' on either side means unassigned
+#[derive(Debug, Clone)]
+pub struct Reassignment {
+    pub from: Option<Employee>,
+    pub to: Option<Employee>,
+    pub on: NaiveDate,
+}
+
+// Status and assignee only change through 'set_status' and
+// 'assign' so the workflow and history stay honest
 #[derive(Debug)]
 pub struct Task {
-    pub assigned_to: Option<Employee>,
+    pub title: String,
+    pub priority: Priority,
+    pub due: Option<NaiveDate>,
+    status: TaskStatus,
+    assigned_to: Option<Employee>,
+    history: Vec<Reassignment>,
+}
+
+// Inherent implementation
+impl Task {
+    pub fn new(title: &str, priority: Priority, due: Option<NaiveDate>) -> Self {
+        Task {
+            title: title.to_string(),
+            priority,
+            due,
+            status: TaskStatus::Todo,
+            assigned_to: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        self.status
+    }
+
+    pub fn assignee(&self) -> Option<&Employee> {
+        self.assigned_to.as_ref()
+    }
+
+    pub fn history(&self) -> &[Reassignment] {
+        &self.history
+    }
+
+    pub fn is_assigned_to(&self, employee: &Employee) -> bool {
+        self.assigned_to.as_ref() == Some(employee)
+    }
+
+    // Finished tasks are never overdue
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.status != TaskStatus::Done && self.due.is_some_and(|due| due < today)
+    }
+
+    pub fn set_status(&mut self, next: TaskStatus) -> Result<(), Error> {
+        if !self.status.can_transition_to(next) {
+            return Err(Error::other(format!(
+                "'{}' can't go from {:?} to {:?}",
+                self.title, self.status, next
+            )));
+        }
+
+        self.status = next;
+        Ok(())
+    }
+
+    // Pass 'None' to unassign; assigning to the current
+    // assignee is a no-op and isn't recorded
+    pub fn assign(&mut self, employee: Option<Employee>, on: NaiveDate) {
+        if self.assigned_to == employee {
+            return;
+        }
+
+        self.history.push(Reassignment {
+            from: self.assigned_to.clone(),
+            to: employee.clone(),
+            on,
+        });
+        self.assigned_to = employee;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TaskTracker {
+    tasks: BTreeMap<u32, Task>,
+    next_id: u32,
+}
+
+// Inherent implementation
+impl TaskTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, task: Task) -> u32 {
+        self.next_id += 1;
+        self.tasks.insert(self.next_id, task);
+        self.next_id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Task> {
+        self.tasks.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Result<&mut Task, Error> {
+        self.tasks
+            .get_mut(&id)
+            .ok_or_else(|| Error::other(format!("no task with id {}", id)))
+    }
+
+    pub fn for_employee(&self, employee: &Employee) -> Vec<(u32, &Task)> {
+        self.query(|task| task.is_assigned_to(employee))
+    }
+
+    pub fn unassigned(&self) -> Vec<(u32, &Task)> {
+        self.query(|task| task.assignee().is_none())
+    }
+
+    pub fn with_status(&self, status: TaskStatus) -> Vec<(u32, &Task)> {
+        self.query(|task| task.status() == status)
+    }
+
+    pub fn overdue(&self, today: NaiveDate) -> Vec<(u32, &Task)> {
+        self.query(|task| task.is_overdue(today))
+    }
+
+    // Most urgent first, then earliest due (undated last),
+    // then oldest task
+    fn query(&self, filter: impl Fn(&Task) -> bool) -> Vec<(u32, &Task)> {
+        let mut tasks: Vec<(u32, &Task)> = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| filter(task))
+            .map(|(id, task)| (*id, task))
+            .collect();
+
+        tasks.sort_by(|(a_id, a), (b_id, b)| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| match (a.due, b.due) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => a.is_none().cmp(&b.is_none()),
+                })
+                .then(a_id.cmp(b_id))
+        });
+
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn employee(id: u32, name: &str) -> Employee {
+        Employee {
+            id,
+            name: String::from(name),
+        }
+    }
+
+    fn ids(tasks: Vec<(u32, &Task)>) -> Vec<u32> {
+        tasks.into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn allowed_transitions() {
+        let mut task = Task::new("Shelve returns", Priority::Normal, None);
+
+        task.set_status(TaskStatus::InProgress).unwrap();
+        task.set_status(TaskStatus::Todo).unwrap();
+        task.set_status(TaskStatus::InProgress).unwrap();
+        task.set_status(TaskStatus::Done).unwrap();
+        assert_eq!(task.status(), TaskStatus::Done);
+
+        // Reopened
+        task.set_status(TaskStatus::InProgress).unwrap();
+        assert_eq!(task.status(), TaskStatus::InProgress);
+    }
+
+    #[test]
+    fn refused_transitions_leave_the_status_alone() {
+        let mut task = Task::new("Shelve returns", Priority::Normal, None);
+
+        for next in [TaskStatus::Todo, TaskStatus::Done] {
+            assert!(task.set_status(next).is_err());
+            assert_eq!(task.status(), TaskStatus::Todo);
+        }
+
+        task.set_status(TaskStatus::InProgress).unwrap();
+        assert!(task.set_status(TaskStatus::InProgress).is_err());
+
+        task.set_status(TaskStatus::Done).unwrap();
+        for next in [TaskStatus::Todo, TaskStatus::Done] {
+            assert!(task.set_status(next).is_err());
+            assert_eq!(task.status(), TaskStatus::Done);
+        }
+    }
+
+    #[test]
+    fn reassignments_are_recorded() {
+        let ann = employee(1, "Ann");
+        let bob = employee(2, "Bob");
+        let mut task = Task::new("Shelve returns", Priority::Normal, None);
+
+        task.assign(Some(ann.clone()), day(1));
+        // Same assignee again isn't a change
+        task.assign(Some(ann.clone()), day(2));
+        task.assign(Some(bob.clone()), day(3));
+        task.assign(None, day(4));
+
+        assert_eq!(task.assignee(), None);
+        let history: Vec<_> = task
+            .history()
+            .iter()
+            .map(|change| (change.from.clone(), change.to.clone(), change.on))
+            .collect();
+        assert_eq!(
+            history,
+            vec![
+                (None, Some(ann.clone()), day(1)),
+                (Some(ann), Some(bob.clone()), day(3)),
+                (Some(bob), None, day(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn employees_with_the_same_name_are_different_people() {
+        let mut task = Task::new("Shelve returns", Priority::Normal, None);
+        task.assign(Some(employee(1, "Ann")), day(1));
+
+        assert!(task.is_assigned_to(&employee(1, "Ann B.")));
+        assert!(!task.is_assigned_to(&employee(2, "Ann")));
+
+        task.assign(Some(employee(2, "Ann")), day(2));
+        assert_eq!(task.history().len(), 2);
+    }
+
+    #[test]
+    fn queries_filter_and_sort_by_urgency_then_due_date() {
+        let ann = employee(1, "Ann");
+        let bob = employee(2, "Bob");
+        let mut tracker = TaskTracker::new();
+
+        let shelve = tracker.add(Task::new("Shelve", Priority::Normal, Some(day(10))));
+        let scanner = tracker.add(Task::new("Scanner", Priority::High, None));
+        let inventory = tracker.add(Task::new("Inventory", Priority::High, Some(day(1))));
+        let labels = tracker.add(Task::new("Labels", Priority::Low, Some(day(2))));
+        let dusting = tracker.add(Task::new("Dusting", Priority::Low, Some(day(3))));
+
+        tracker
+            .get_mut(shelve)
+            .unwrap()
+            .assign(Some(ann.clone()), day(1));
+        tracker
+            .get_mut(inventory)
+            .unwrap()
+            .assign(Some(ann.clone()), day(1));
+        tracker
+            .get_mut(scanner)
+            .unwrap()
+            .assign(Some(bob.clone()), day(1));
+
+        // Done tasks are never overdue
+        let done = tracker.get_mut(dusting).unwrap();
+        done.set_status(TaskStatus::InProgress).unwrap();
+        done.set_status(TaskStatus::Done).unwrap();
+
+        assert_eq!(ids(tracker.for_employee(&ann)), vec![inventory, shelve]);
+        assert_eq!(ids(tracker.for_employee(&bob)), vec![scanner]);
+        assert_eq!(ids(tracker.unassigned()), vec![labels, dusting]);
+        assert_eq!(ids(tracker.overdue(day(5))), vec![inventory, labels]);
+        assert_eq!(ids(tracker.overdue(day(1))), Vec::<u32>::new());
+        assert_eq!(ids(tracker.with_status(TaskStatus::Done)), vec![dusting]);
+        assert!(tracker.get_mut(99).is_err());
+    }
 }
@@ -0,0 +1,253 @@
+use std::collections::btree_map::BTreeMap;
+use std::collections::btree_set::{BTreeSet, Range};
+use std::io::Error;
+use std::ops::Bound;
+
+use chrono::{DateTime, Utc};
+
+use super::catalog::{Catalog, MediaId};
+use super::media::Media;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Title,
+    // Author or director
+    Creator,
+    Kind,
+    DateAdded,
+}
+
+const ALL: [SortBy; 4] = [
+    SortBy::Title,
+    SortBy::Creator,
+    SortBy::Kind,
+    SortBy::DateAdded,
+];
+
+// Inherent implementation
+impl SortBy {
+    fn slot(&self) -> usize {
+        match self {
+            SortBy::Title => 0,
+            SortBy::Creator => 1,
+            SortBy::Kind => 2,
+            SortBy::DateAdded => 3,
+        }
+    }
+
+    fn code(&self) -> char {
+        match self {
+            SortBy::Title => 't',
+            SortBy::Creator => 'c',
+            SortBy::Kind => 'k',
+            SortBy::DateAdded => 'd',
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        ALL.into_iter().find(|sort| code.chars().eq([sort.code()]))
+    }
+
+    // Text sorts ignore case and put items without the
+    // field (placeholders, podcasts without an author) last
+    fn key(&self, media: &Media, added: DateTime<Utc>) -> SortKey {
+        let text = |text: Option<&str>| match text {
+            Some(text) => SortKey(0, text.to_lowercase()),
+            None => SortKey(1, String::new()),
+        };
+
+        match self {
+            SortBy::Title => text(media.title()),
+            SortBy::Creator => text(media.creator()),
+            SortBy::Kind => SortKey(media.kind() as i64, String::new()),
+            SortBy::DateAdded => SortKey(
+                added.timestamp_nanos_opt().unwrap_or(i64::MAX),
+                String::new(),
+            ),
+        }
+    }
+}
+
+// A number compared first, then text; the media id breaks
+// any remaining ties so every position is unique
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SortKey(i64, String);
+
+/*
+    ### Cursors ###
+    - A cursor remembers the sort key and id of the last
+    item handed out, not an offset, so items added (or
+    removed) while someone is paging never cause repeats
+    or skips among the others
+    - Items added behind the cursor won't show up on the
+    following pages; items added ahead of it will
+    - 'to_token' / 'from_token' turn a cursor into a string
+    the UI can hand back
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub sort: SortBy,
+    key: SortKey,
+    id: MediaId,
+}
+
+// Inherent implementation
+impl Cursor {
+    // 't.12.0.interstellar' - sort, id, then the key
+    pub fn to_token(&self) -> String {
+        format!(
+            "{}.{}.{}.{}",
+            self.sort.code(),
+            self.id.0,
+            self.key.0,
+            self.key.1
+        )
+    }
+
+    pub fn from_token(token: &str) -> Result<Self, Error> {
+        let invalid = || Error::other(format!("invalid cursor '{}'", token));
+
+        let mut parts = token.splitn(4, '.');
+        let sort = parts
+            .next()
+            .and_then(SortBy::from_code)
+            .ok_or_else(invalid)?;
+        let id = parts
+            .next()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(invalid)?;
+        let number = parts
+            .next()
+            .and_then(|number| number.parse().ok())
+            .ok_or_else(invalid)?;
+        let text = parts.next().ok_or_else(invalid)?;
+
+        Ok(Cursor {
+            sort,
+            key: SortKey(number, text.to_string()),
+            id: MediaId(id),
+        })
+    }
+}
+
+// One ordered set per 'SortBy', kept in step with the
+// catalog by 'insert' / 'remove'
+#[derive(Debug, Default)]
+pub struct SortedViews {
+    views: [BTreeSet<(SortKey, MediaId)>; 4],
+}
+
+// Inherent implementation
+impl SortedViews {
+    pub fn insert(&mut self, id: MediaId, media: &Media, added: DateTime<Utc>) {
+        for sort in ALL {
+            self.views[sort.slot()].insert((sort.key(media, added), id));
+        }
+    }
+
+    pub fn remove(&mut self, id: MediaId, media: &Media, added: DateTime<Utc>) {
+        for sort in ALL {
+            self.views[sort.slot()].remove(&(sort.key(media, added), id));
+        }
+    }
+
+    // From the start, or from just after 'after'
+    pub fn sorted<'a>(
+        &'a self,
+        sort: SortBy,
+        after: Option<&Cursor>,
+        items: &'a BTreeMap<MediaId, Media>,
+    ) -> Sorted<'a> {
+        let view = &self.views[sort.slot()];
+        let range = match after {
+            Some(cursor) => view.range((
+                Bound::Excluded((cursor.key.clone(), cursor.id)),
+                Bound::Unbounded,
+            )),
+            None => view.range::<(SortKey, MediaId), _>(..),
+        };
+
+        Sorted {
+            sort,
+            range,
+            items,
+            last: None,
+        }
+    }
+}
+
+// Borrowed items in sort order; 'cursor' marks where the
+// iterator has got to
+pub struct Sorted<'a> {
+    sort: SortBy,
+    range: Range<'a, (SortKey, MediaId)>,
+    items: &'a BTreeMap<MediaId, Media>,
+    last: Option<&'a (SortKey, MediaId)>,
+}
+
+// Inherent implementation
+impl Sorted<'_> {
+    // 'None' until the first item has been taken
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.last.map(|(key, id)| Cursor {
+            sort: self.sort,
+            key: key.clone(),
+            id: *id,
+        })
+    }
+}
+
+impl<'a> Iterator for Sorted<'a> {
+    type Item = (MediaId, &'a Media);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.range.next()?;
+        self.last = Some(entry);
+
+        Some((entry.1, &self.items[&entry.1]))
+    }
+}
+
+#[derive(Debug)]
+pub struct Page<'a> {
+    pub items: Vec<(MediaId, &'a Media)>,
+    // 'None' on the last page
+    pub next: Option<Cursor>,
+}
+
+// Inherent implementation
+impl Catalog {
+    // An empty page would have nowhere to put its cursor, so
+    // 'limit' must be at least 1
+    pub fn page(
+        &self,
+        sort: SortBy,
+        after: Option<&Cursor>,
+        limit: usize,
+    ) -> Result<Page<'_>, Error> {
+        if limit == 0 {
+            return Err(Error::other("page limit must be at least 1"));
+        }
+
+        let mut sorted = match after {
+            Some(cursor) if cursor.sort != sort => {
+                return Err(Error::other(format!(
+                    "cursor is for {:?}, not {:?}",
+                    cursor.sort, sort
+                )));
+            }
+            Some(cursor) => self.sorted_after(cursor),
+            None => self.sorted(sort),
+        };
+
+        let items: Vec<(MediaId, &Media)> = sorted.by_ref().take(limit).collect();
+
+        // Only hand out a cursor if something follows it
+        let next = match sorted.range.clone().next() {
+            Some(_) => sorted.cursor().or_else(|| after.cloned()),
+            None => None,
+        };
+
+        Ok(Page { items, next })
+    }
+}
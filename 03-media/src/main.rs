@@ -1,33 +1,722 @@
 mod content;
 
+use std::collections::HashSet;
+use std::io::Error;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+
 // use content::catalog::{Catalog, MightHaveAValue};
 use content::catalog::Catalog;
-use content::catalog::MightHaveAValue;
+use content::catalog::{MediaId, MightHaveAValue};
+use content::circulation::{Circulation, Policy};
 use content::employee::Employee;
-use content::media::Media;
-use content::task::Task;
+use content::index::{stem, tokenize};
+use content::media::{Media, MediaKind};
+use content::metadata::{Identifier, Metadata, Rating};
+use content::query::Query;
+use content::recommend::{ConsumptionLog, Recommender, Signal, Weights};
+use content::render::{Renderer, Style, TemplateRenderer, Templates};
+use content::store::CatalogStore;
+use content::task::{Priority, Task, TaskStatus, TaskTracker};
+use content::view::{Cursor, SortBy};
 
 // Immutable reference of Media
 fn print_media(media: &Media) {
     println!("{:#?}", media);
 }
 
-fn main() {
+fn search_demo(catalog: &Catalog) {
+    // (Books by "author" OR anything tagged staff-pick)
+    // AND released 2000 or later
+    let query = Query::kind(MediaKind::Book)
+        .and(Query::creator("AUTHOR"))
+        .or(Query::tag("staff-pick"))
+        .and(Query::years(Some(2000), None));
+
+    for (id, media) in catalog.search(&query) {
+        println!("Match {}: {}", id, media.description());
+    }
+
+    let query = Query::title("interSTELLAR").or(Query::kind(MediaKind::Podcast));
+    let found: Vec<(MediaId, &Media)> = catalog.search(&query).collect();
+    println!("Title or podcast: {} item(s)", found.len());
+}
+
+fn full_text_demo(catalog: &mut Catalog, placeholder_id: MediaId) {
+    // "Dramas" and "drama" stem to the same term
+    for (id, media, score) in catalog.search_text("interstellar dramas") {
+        println!("{:.3} {} {}", score, id, media.description());
+    }
+
+    // Removing an item drops it from the index too
+    println!(
+        "'placeholder': {}",
+        catalog.search_text("placeholder").len()
+    );
+    if let Some(removed) = catalog.remove(placeholder_id) {
+        println!("Removed: {}", removed.description());
+    }
+    println!(
+        "'placeholder': {}",
+        catalog.search_text("placeholder").len()
+    );
+    println!("'brief': {}", catalog.search_text("brief").len());
+
+    let token_count = tokenize("The Running of the Stars").len();
+    println!("Tokens: {} ({})", token_count, stem("stories"));
+}
+
+fn identity_demo(catalog: &mut Catalog, book_id: MediaId) -> Result<(), Error> {
+    // Same title and author in a different case - rejected
+    let copy = Media::Book {
+        author: String::from("an author"),
+        title: String::from("A BOOK"),
+        metadata: Metadata::default(),
+    };
+    if let Err(error) = catalog.add(copy) {
+        println!("Add rejected: {}", error);
+    }
+
+    // Same ISBN under another title - also a duplicate
+    let reprint = Media::Book {
+        author: String::from("Someone else"),
+        title: String::from("A book (reprint)"),
+        metadata: Metadata {
+            identifiers: vec![Identifier::Isbn(String::from("978-0-00-000000-2"))],
+            ..Default::default()
+        },
+    };
+    if let Err(error) = catalog.add(reprint) {
+        println!("Add rejected: {}", error);
+    }
+
+    let second_edition = Media::Book {
+        author: String::from("An author"),
+        title: String::from("A book"),
+        metadata: Metadata {
+            year: Some(2021),
+            tags: vec![String::from("staff-pick")],
+            ..Default::default()
+        },
+    };
+    let previous = catalog.update(book_id, second_edition)?;
+    println!("Updated {}: was '{}'", book_id, previous.description());
+    println!(
+        "Now {}: {:?}",
+        book_id,
+        catalog.get(book_id).map(|media| media.description())
+    );
+
+    if let Err(error) = catalog.update(MediaId(404), Media::Placeholder) {
+        println!("Update rejected: {}", error);
+    }
+
+    println!("{} item(s), empty: {}", catalog.len(), catalog.is_empty());
+
+    Ok(())
+}
+
+fn exchange_demo(catalog: &Catalog) -> Result<(), Error> {
+    // Every format reads back into an identical catalog;
+    // the exports are scratch files, so they go to the
+    // temp dir
+    for name in ["catalog.json", "catalog.csv", "catalog.mrk"] {
+        let path = std::env::temp_dir().join(name);
+        catalog.export_file(&path)?;
+
+        let mut copy = Catalog::new();
+        let report = copy.import_file(&path)?;
+
+        println!(
+            "{}: {} item(s), round trip exact: {}",
+            name,
+            report.imported.len(),
+            copy.to_json()? == catalog.to_json()?
+        );
+    }
+
+    // Values a text format couldn't read back as written are
+    // refused up front
+    let mut unreadable = Catalog::new();
+    let fake_isbn = Media::Book {
+        author: String::from("An author"),
+        title: String::from("Another book"),
+        metadata: Metadata {
+            identifiers: vec![Identifier::Other {
+                scheme: String::from("ISBN"),
+                value: String::from("978-0"),
+            }],
+            ..Default::default()
+        },
+    };
+    if let Err(error) = unreadable.add(fake_isbn) {
+        println!("Add rejected: {}", error);
+    }
+
+    // Librarian-authored file: no ids, a few broken rows
+    let mut imported = Catalog::new();
+    let report = imported.import_file("import.csv")?;
+    println!("import.csv: {}", report.summary());
+
+    if let Err(error) =
+        imported.import_csv("kind,title,author,title\nBook,Emma,Jane Austen,Persuasion\n")
+    {
+        println!("CSV refused: {}", error);
+    }
+
+    let json = r#"[
+        {"kind": "Book", "title": "Emma"},
+        {"kind": "Book", "author": "Jane Austen", "title": "Emma"},
+        {"kind": "Book", "author": "Jane Austen", "title": "Emma", "metadata": {"year": 1815}},
+        {"kind": "Book",
+         "author": "Jane Austen", "title": "Persuasion"}
+    ]"#;
+    let report = imported.import_json(json)?;
+    println!("JSON: {}", report.summary());
+
+    let marc = "=LDR  Movie\n=100  Ridley Scott\n=245  Alien\n=264  1979\n\n=LDR  Movie\n=245  Alien\n=490  Not a podcast\n";
+    println!("MARC-lite: {}", imported.import_marc(marc).summary());
+
+    Ok(())
+}
+
+fn circulation_demo(catalog: &Catalog) -> Result<(), Error> {
+    let day = |month: u32, day: u32| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    let ids: Vec<MediaId> = catalog.iter().map(|(id, _)| id).collect();
+
+    let mut circulation = Circulation::new(Policy::default());
+    circulation.add_patron(
+        1,
+        Employee {
+            id: 1,
+            name: String::from("Mob Boss"),
+        },
+    )?;
+    circulation.add_patron(
+        2,
+        Employee {
+            id: 2,
+            name: String::from("Hit Man"),
+        },
+    )?;
+
+    let due = circulation.checkout(catalog, ids[0], 1, day(3, 1))?;
+    println!("{} due back {}", ids[0], due);
+    println!(
+        "Queue position: {}",
+        circulation.place_hold(catalog, ids[0], 2)?
+    );
+
+    if let Err(error) = circulation.renew(ids[0], 1, day(3, 10)) {
+        println!("Renewal refused: {}", error);
+    }
+
+    for (media_id, loan, days) in circulation.overdue(day(3, 30)) {
+        println!("{} overdue by {} day(s), due {}", media_id, days, loan.due);
+    }
+
+    if let Some(fine) = circulation.return_item(ids[0], day(3, 30))? {
+        println!(
+            "Fine for patron {}: {} cents ({} day(s) late)",
+            fine.patron_id, fine.cents, fine.days_overdue
+        );
+    }
+    println!("{} is now {:?}", ids[0], circulation.state(ids[0]));
+
+    if let Some(patron) = circulation.patron(2) {
+        println!(
+            "Waiting for: {} (patron {})",
+            patron.employee.name, patron.id
+        );
+    }
+    circulation.checkout(catalog, ids[0], 2, day(4, 2))?;
+
+    circulation.checkout(catalog, ids[1], 1, day(4, 2))?;
+    circulation.place_hold(catalog, ids[1], 2)?;
+    circulation.cancel_hold(ids[1], 2)?;
+    println!("Renewed to {}", circulation.renew(ids[1], 1, day(4, 20))?);
+    println!("Loans of patron 1: {}", circulation.loans_for(1).len());
+    println!("Holds on {}: {:?}", ids[1], circulation.holds_queue(ids[1]));
+    println!(
+        "Expired from shelf: {:?}",
+        circulation.expire_hold_shelf(day(5, 1))
+    );
+
+    let owed = circulation.outstanding_cents(1);
+    println!(
+        "Patron 1 owes {} cents over {} fine(s); {} left after paying",
+        owed,
+        circulation.fines_for(1).len(),
+        circulation.pay_fines(1, owed)?
+    );
+
+    Ok(())
+}
+
+fn task_demo(boss: Employee, t_1: Task, t_2: Task, today: NaiveDate) -> Result<(), Error> {
+    let hit_man = Employee {
+        id: 2,
+        name: String::from("Hit Man"),
+    };
+
+    let mut tracker = TaskTracker::new();
+    let shelve = tracker.add(t_1);
+    let scanner = tracker.add(t_2);
+    let inventory = tracker.add(Task::new(
+        "Yearly inventory",
+        Priority::Urgent,
+        NaiveDate::from_ymd_opt(2024, 3, 1),
+    ));
+    tracker.add(Task::new("Water the plants", Priority::Low, None));
+
+    tracker
+        .get_mut(inventory)?
+        .assign(Some(boss.clone()), today);
+    tracker
+        .get_mut(inventory)?
+        .assign(Some(hit_man.clone()), today);
+    tracker
+        .get_mut(inventory)?
+        .set_status(TaskStatus::InProgress)?;
+
+    tracker
+        .get_mut(shelve)?
+        .set_status(TaskStatus::InProgress)?;
+    tracker.get_mut(shelve)?.set_status(TaskStatus::Done)?;
+    if let Err(error) = tracker.get_mut(scanner)?.set_status(TaskStatus::Done) {
+        println!("Refused: {}", error);
+    }
+
+    for (employee, label) in [(&boss, "boss"), (&hit_man, "hit man")] {
+        for (id, task) in tracker.for_employee(employee) {
+            println!(
+                "Task {} for {}: {} ({:?})",
+                id,
+                label,
+                task.title,
+                task.status()
+            );
+        }
+    }
+
+    for (id, task) in tracker.unassigned() {
+        println!(
+            "Unassigned task {}: {} ({:?})",
+            id, task.title, task.priority
+        );
+    }
+    println!("Done: {}", tracker.with_status(TaskStatus::Done).len());
+
+    for (id, task) in tracker.overdue(today) {
+        println!("Overdue task {}: {} (due {:?})", id, task.title, task.due);
+    }
+
+    if let Some(task) = tracker.get(inventory) {
+        for change in task.history() {
+            println!(
+                "{}: {:?} -> {:?}",
+                change.on,
+                change.from.as_ref().map(|employee| &employee.name),
+                change.to.as_ref().map(|employee| &employee.name)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn series_demo(catalog: &mut Catalog) -> Result<(), Error> {
+    let show = "A Brief History of Nearly Everything";
+    let mut played = HashSet::new();
+
+    for (number, title) in [(100, "Before the Big Bang"), (103, "Stardust")] {
+        let id = catalog.add(Media::Podcast {
+            show: String::from(show),
+            season: None,
+            episode_number: number,
+            episode_title: String::from(title),
+            metadata: Metadata::default(),
+        })?;
+        if number == 100 {
+            played.insert(id);
+        }
+    }
+
+    for (season, number, title) in [(1, 2, "The Lab"), (1, 1, "Pilot"), (3, 1, "Return")] {
+        catalog.add(Media::TvEpisode {
+            series: String::from("Deep Space"),
+            season,
+            episode_number: number,
+            episode_title: String::from(title),
+            metadata: Metadata::default(),
+        })?;
+    }
+
+    println!("Series: {:?}", catalog.series_names());
+
+    for series in [show, "deep space"] {
+        for episode in catalog.episodes(series) {
+            println!(
+                "  {} ({:?}, {}): {}",
+                episode.id,
+                episode.season,
+                episode.number,
+                episode.media.description()
+            );
+        }
+        println!("  Missing: {:?}", catalog.missing_episodes(series));
+        println!(
+            "  Next up: {:?}",
+            catalog
+                .next_unplayed(series, &played)
+                .map(|episode| episode.media.description())
+        );
+    }
+
+    Ok(())
+}
+
+fn recommendation_demo(catalog: &mut Catalog) -> Result<(), Error> {
+    let sequel = catalog.add(Media::Book {
+        author: String::from("An author"),
+        title: String::from("Another book"),
+        metadata: Metadata {
+            genres: vec![String::from("Drama")],
+            ..Default::default()
+        },
+    })?;
+    let arrival = catalog.add(Media::Movie {
+        director: String::from("Denis Villeneuve"),
+        title: String::from("Arrival"),
+        metadata: Metadata {
+            genres: vec![String::from("Sci-Fi")],
+            ..Default::default()
+        },
+    })?;
+
+    let ids: Vec<MediaId> = catalog.iter().map(|(id, _)| id).collect();
+    let (book, movie) = (ids[1], ids[2]);
+
+    // User 1 read the book and watched the movie; users 2
+    // and 3 watched the movie and 'Arrival'
+    let mut log = ConsumptionLog::new();
+    log.record(1, book);
+    log.record(1, movie);
+    for user_id in [2, 3] {
+        log.record(user_id, movie);
+        log.record(user_id, arrival);
+    }
+    println!("User 1 history: {:?}", log.history(1));
+
+    let recommender = Recommender::new(Weights::default());
+    for recommendation in recommender.for_user(catalog, &log, 1, 3) {
+        println!(
+            "{:.2} {}: {}",
+            recommendation.score,
+            recommendation.id,
+            recommendation.media.description()
+        );
+        for reason in &recommendation.reasons {
+            println!("    {}", reason);
+        }
+    }
+
+    let similar = Recommender::default().more_like(catalog, &log, sequel, 1);
+    for recommendation in similar {
+        let signals: Vec<&Signal> = recommendation
+            .reasons
+            .iter()
+            .map(|reason| &reason.signal)
+            .collect();
+        println!("Like {}: {} {:?}", sequel, recommendation.id, signals);
+    }
+
+    Ok(())
+}
+
+fn pagination_demo(catalog: &mut Catalog) -> Result<(), Error> {
+    let page = catalog.page(SortBy::Title, None, 4)?;
+    for (id, media) in &page.items {
+        println!("  {} {:?}", id, media.title());
+    }
+
+    // The UI keeps the token, not the page number
+    let token = page.next.map(|cursor| cursor.to_token());
+    println!("Next page token: {:?}", token);
+
+    // Added while the user is on page 1: the first sorts
+    // before the cursor, the second after it
+    for title in ["Aardvark", "Zebra"] {
+        catalog.add(Media::Book {
+            author: String::from("Late Arrival"),
+            title: String::from(title),
+            metadata: Metadata::default(),
+        })?;
+    }
+
+    let mut after = token.map(|token| Cursor::from_token(&token)).transpose()?;
+    while let Some(cursor) = after {
+        let page = catalog.page(SortBy::Title, Some(&cursor), 4)?;
+        println!("Page after {}:", cursor.to_token());
+        for (id, media) in &page.items {
+            println!("  {} {:?}", id, media.title());
+        }
+        after = page.next;
+    }
+
+    let mut by_kind = catalog.sorted(SortBy::Kind);
+    let first_two: Vec<MediaKind> = by_kind
+        .by_ref()
+        .take(2)
+        .map(|(_, media)| media.kind())
+        .collect();
+    println!(
+        "By kind: {:?}, then {:?}",
+        first_two,
+        by_kind.next().map(|(_, media)| media.kind())
+    );
+
+    if let Some(cursor) = by_kind.cursor() {
+        println!("Resumed: {}", catalog.sorted_after(&cursor).count());
+    }
+
+    let creators: Vec<Option<&str>> = catalog
+        .sorted(SortBy::Creator)
+        .map(|(_, media)| media.creator())
+        .take(3)
+        .collect();
+    println!("By creator: {:?}", creators);
+
+    if let Some((id, _)) = catalog.sorted(SortBy::DateAdded).last() {
+        let added = catalog
+            .added_at(id)
+            .map(|at| at.format("%Y-%m-%d").to_string());
+        println!("Newest: {} added {:?}", id, added);
+    }
+
+    if let Err(error) = catalog.page(SortBy::Kind, Some(&Cursor::from_token("t.1.0.a")?), 1) {
+        println!("Page refused: {}", error);
+    }
+
+    if let Err(error) = catalog.page(SortBy::Title, None, 0) {
+        println!("Page refused: {}", error);
+    }
+
+    Ok(())
+}
+
+fn scanner_demo() -> Result<(), Error> {
+    let mut catalog = Catalog::new();
+    let report = catalog.scan_directory("library")?;
+
+    for (path, id) in &report.added {
+        let description = catalog.get(*id).map(|media| media.description());
+        println!("{} {}: {:?}", path.display(), id, description);
+    }
+    for skipped in report.unrecognized.iter().chain(&report.rejected) {
+        println!("Skipped {}: {}", skipped.path.display(), skipped.reason);
+    }
+
+    // Scanning again finds nothing new
+    let again = catalog.scan_directory("library")?;
+    println!(
+        "Rescan: {} added, {} rejected",
+        again.added.len(),
+        again.rejected.len()
+    );
+
+    Ok(())
+}
+
+fn store_demo(catalog: &Catalog) -> Result<(), Error> {
+    let path = std::env::temp_dir().join("media-catalog.db");
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    let mut store = CatalogStore::open(&path)?;
+    println!("Schema version: {}", store.schema_version()?);
+
+    let mut ids = Vec::new();
+    for (_, media) in catalog.iter() {
+        ids.push(store.add(media.clone())?);
+    }
+    println!("Stored {} items as {:?}", ids.len(), ids);
+
+    if let Some((_, media)) = catalog.iter().next() {
+        if let Err(error) = store.add(media.clone()) {
+            println!("Not stored: {}", error);
+        }
+    }
+
+    let removed = store.remove(ids[0])?;
+    println!(
+        "Removed {}: {:?}",
+        ids[0],
+        removed.as_ref().map(|media| media.description())
+    );
+
+    // A second connection puts it back; the first one sees
+    // that row when it checks for duplicates
+    if let Some(media) = removed {
+        let mut other = CatalogStore::open(&path)?;
+        let restored = other.add(media.clone())?;
+        if let Err(error) = store.add(media) {
+            println!("Not stored again: {}", error);
+        }
+        other.remove(restored)?;
+    }
+
+    if let Some(mut media) = store.get(ids[1])? {
+        if let Media::Book { title, .. } = &mut media {
+            title.push_str(", revised");
+        }
+        let previous = store.update(ids[1], media)?;
+        println!("Updated {}, was {}", ids[1], previous.description());
+    }
+
+    // Opening again finds the schema current and the items
+    // still there
+    drop(store);
+    let store = CatalogStore::open(&path)?;
+    println!(
+        "Reopened: {} items, empty: {}",
+        store.len()?,
+        store.is_empty()?
+    );
+    for id in [ids[0], ids[1]] {
+        let description = store.get(id)?.map(|media| media.description());
+        println!("{}: {:?}", id, description);
+    }
+
+    let (loaded, skipped) = store.load()?;
+    for row in &skipped {
+        println!("Skipped {}: {}", row.id, row.reason);
+    }
+    let found: Vec<_> = loaded
+        .search_text("interstellar")
+        .into_iter()
+        .map(|(id, media, _)| format!("{} {}", id, media.description()))
+        .collect();
+    println!("Loaded {} items, search finds {:?}", loaded.len(), found);
+
+    Ok(())
+}
+
+// A renderer of our own, next to the built-in ones
+struct Spine;
+
+impl Renderer for Spine {
+    fn render(&self, media: &Media) -> String {
+        media.title().unwrap_or("?").to_uppercase()
+    }
+}
+
+fn render_demo(catalog: &Catalog) -> Result<(), Error> {
+    let english = Templates::english();
+    let german = Templates::load("locales", "de")?;
+    let japanese = Templates::load("locales", "ja")?;
+
+    let picks: Vec<&Media> = catalog
+        .iter()
+        .map(|(_, media)| media)
+        .filter(|media| {
+            media.kind() != MediaKind::Podcast
+                || media
+                    .series_position()
+                    .is_some_and(|(_, _, number)| number == 101)
+        })
+        .take(4)
+        .chain(
+            catalog
+                .iter()
+                .map(|(_, media)| media)
+                .filter(|media| media.kind() == MediaKind::TvEpisode)
+                .take(1),
+        )
+        .collect();
+
+    for templates in [&english, &german, &japanese] {
+        println!("[{}]", templates.language);
+        for style in [Style::Short, Style::Long, Style::Apa, Style::Mla] {
+            let renderer = TemplateRenderer { style, templates };
+            for media in &picks {
+                println!("  {:?}: {}", style, media.render(&renderer));
+            }
+        }
+    }
+
+    for media in &picks {
+        println!("Spine: {}", media.render(&Spine));
+    }
+
+    // 'Display' is the English short style
+    if let Some(media) = picks.first() {
+        println!("Display: {}", media);
+    }
+
+    if let Err(error) = Templates::load("locales", "fr") {
+        println!("No French yet: {}", error);
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
     // In the next line instead of
     // 'content::media::Media::AudioBook' we can also use
     // 'Media::AudioBook'
     let any_audio_book = content::media::Media::AudioBook {
+        author: String::from("Robin Sharma"),
         title: String::from("Who will cry when you will die?"),
+        metadata: Metadata {
+            year: Some(1999),
+            duration: Some(Duration::from_secs(4 * 3600 + 12 * 60)),
+            language: Some(String::from("English")),
+            ..Default::default()
+        },
     };
     let any_book = Media::Book {
         author: String::from("An author"),
         title: String::from("A book"),
+        metadata: Metadata {
+            genres: vec![String::from("Fiction")],
+            identifiers: vec![Identifier::Isbn(String::from("978-0-00-000000-2"))],
+            tags: vec![String::from("Staff-Pick")],
+            ..Default::default()
+        },
     };
     let any_movie = Media::Movie {
         director: String::from("A director"),
         title: String::from("Interstellar"),
+        metadata: Metadata {
+            year: Some(2014),
+            genres: vec![String::from("Sci-Fi"), String::from("Drama")],
+            duration: Some(Duration::from_secs(169 * 60)),
+            identifiers: vec![
+                Identifier::Imdb(String::from("tt0816692")),
+                Identifier::Other {
+                    scheme: String::from("EIDR"),
+                    value: String::from("10.5240/0F2A-6B4E-1B3C-4D5E-6F70-X"),
+                },
+            ],
+            language: Some(String::from("English")),
+            ratings: vec![Rating {
+                source: String::from("IMDb"),
+                score: 8.7,
+                out_of: 10.0,
+            }],
+            tags: vec![String::from("staff-pick")],
+        },
+    };
+    let any_podcast = Media::Podcast {
+        show: String::from("A Brief History of Nearly Everything"),
+        season: None,
+        episode_number: 101,
+        episode_title: String::from("The Big Bang"),
+        metadata: Metadata::default(),
     };
-    let any_podcast = Media::Podcast(101, String::from("A Brief History of Nearly Everything"));
     let any_placeholder = Media::Placeholder;
 
     println!("{}", any_audio_book.description());
@@ -36,7 +725,7 @@ fn main() {
     println!("{}", any_podcast.description());
     println!("{}", any_placeholder.description());
 
-    println!("");
+    println!();
 
     print_media(&any_audio_book);
     print_media(&any_book);
@@ -44,26 +733,29 @@ fn main() {
     print_media(&any_podcast);
     print_media(&any_placeholder);
 
-    println!("");
+    println!();
 
     let mut catalog = Catalog::new();
 
-    catalog.add(any_audio_book);
-    catalog.add(any_book);
-    catalog.add(any_movie);
-    catalog.add(any_placeholder);
-    catalog.add(any_podcast);
+    let audio_book_id = catalog.add(any_audio_book)?;
+    let book_id = catalog.add(any_book)?;
+    catalog.add(any_movie)?;
+    let placeholder_id = catalog.add(any_placeholder)?;
+    let podcast_id = catalog.add(any_podcast)?;
 
     println!("{:#?}", catalog);
 
-    // Indexing into 'items' - 'Some'; 'get' is a method
-    // on vectors
-    println!("{:#?}", catalog.items.get(4));
+    println!();
+
+    search_demo(&catalog);
+
+    // Looking up by id - 'Some'; ids come from 'add'
+    println!("{:#?}", catalog.get(podcast_id));
 
     // 'None'
-    println!("{:#?}", catalog.items.get(100));
+    println!("{:#?}", catalog.get(MediaId(100)));
 
-    println!("");
+    println!();
 
     /*
         ### Notes ###
@@ -77,7 +769,7 @@ fn main() {
             have a value and the case in which you don't
     */
 
-    let item_0 = catalog.items.get(10);
+    let item_0 = catalog.get(MediaId(10));
     match item_0 {
         Option::Some(value) => {
             // Here we can add another 'match' statement
@@ -87,13 +779,13 @@ fn main() {
             println!("Item: {:#?}", value);
         }
         Option::None => {
-            println!("Nothing at that index");
+            println!("Nothing with that id");
         }
     }
 
-    println!("");
+    println!();
 
-    let item_1 = catalog.get_by_index(20);
+    let item_1 = catalog.lookup(MediaId(20));
     match item_1 {
         MightHaveAValue::ThereIsAValue(value) => {
             println!("Item: {:#?}", value);
@@ -103,9 +795,9 @@ fn main() {
         }
     }
 
-    println!("");
+    println!();
 
-    let item_2 = catalog.get_by_index_new(9999);
+    let item_2 = catalog.get(MediaId(9999));
     match item_2 {
         Option::Some(value) => {
             println!("Item (match): {:#?}", value);
@@ -115,9 +807,9 @@ fn main() {
         }
     }
 
-    println!("");
+    println!();
 
-    let item_3 = catalog.get_by_index(30);
+    let item_3 = catalog.lookup(MediaId(30));
     /*
         ### if let ###
         - Type Assertion: Rust does not have explicit type
@@ -137,16 +829,16 @@ fn main() {
         println!("Got no value!");
     }
 
-    println!("");
+    println!();
 
-    let item_4 = catalog.get_by_index_new(10);
+    let item_4 = catalog.get(MediaId(10));
     if let Some(value) = item_4 {
         println!("Item in pattern match (if let): {:#?}", value);
     } else {
         println!("Got no value (if let)!");
     }
 
-    println!("");
+    println!();
 
     /*
         ### Handling options ###
@@ -175,37 +867,90 @@ fn main() {
         - Documentation
             - https://doc.rust-lang.org/std/option/enum.Option.html
     */
-    let item_for_unwrap = catalog.get_by_index_new(0);
+    let item_for_unwrap = catalog.get(audio_book_id);
     let placeholder = Media::Placeholder;
 
     // println!("{:#?}", item_for_unwrap.unwrap());
     // println!("{:#?}", item_for_unwrap.expect("no item found!"));
     println!("{:#?}", item_for_unwrap.unwrap_or(&placeholder));
 
-    println!("");
+    println!();
 
     let employee1 = Employee {
+        id: 1,
         name: String::from("Mob Boss"),
     };
 
-    let t_1 = Task {
-        assigned_to: Some(employee1),
-    };
+    let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+
+    let mut t_1 = Task::new("Shelve returns", Priority::Normal, None);
+    t_1.assign(Some(employee1.clone()), today);
 
-    let t_2 = Task { assigned_to: None };
+    let t_2 = Task::new("Fix the scanner", Priority::High, None);
 
     println!("t_1: {:#?}", t_1);
 
-    println!("");
+    println!();
+
+    // 'unwrap' here used to panic for unassigned tasks -
+    // 'if let' / 'map_or' handle both cases
+    if let Some(employee) = t_1.assignee() {
+        println!("t_1 is assigned to: {:#?}", employee.name);
+    }
+
+    println!();
 
     println!(
-        "t_1.assigned_to.unwrap().name: {:#?}",
-        t_1.assigned_to.unwrap().name
+        "t_2 is assigned to: {}",
+        t_2.assignee()
+            .map_or("nobody", |employee| employee.name.as_str())
     );
 
-    println!("");
+    println!();
+
+    task_demo(employee1, t_1, t_2, today)?;
+
+    println!();
+
+    full_text_demo(&mut catalog, placeholder_id);
+
+    println!();
+
+    identity_demo(&mut catalog, book_id)?;
+
+    println!();
+
+    series_demo(&mut catalog)?;
+
+    println!();
+
+    recommendation_demo(&mut catalog)?;
 
-    println!("t_2.assigned_to: {:#?}", t_2.assigned_to);
+    println!();
+
+    exchange_demo(&catalog)?;
+
+    println!();
+
+    circulation_demo(&catalog)?;
+
+    println!();
+
+    pagination_demo(&mut catalog)?;
+
+    println!();
+
+    scanner_demo()?;
+
+    println!();
+
+    render_demo(&catalog)?;
+
+    println!();
+
+    store_demo(&catalog)?;
 
     // Exercise link: https://play.rust-lang.org/?version=stable&mode=debug&edition=2021&gist=8cab45161489fe0a2ad027d5222cb3fa
+
+    Ok(())
 }